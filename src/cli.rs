@@ -42,6 +42,16 @@ pub enum Command {
             help = "Require to override all specified templates"
         )]
         override_all: bool,
+        #[structopt(
+            long = "--strict",
+            help = "Fail when any placeholder in templates can't be resolved"
+        )]
+        strict: bool,
+        #[structopt(
+            long = "--seed",
+            help = "Seed for the generator functions to get the reproducible output"
+        )]
+        seed: Option<u64>,
         #[structopt(
             name = "templates",
             help = "Used templates for a project generation."
@@ -1,15 +1,19 @@
 use std::collections::HashMap;
+use std::sync::Arc;
+
+use indexmap::IndexMap;
 
 use crate::cli::{Command, EntityTypeEnum, InstallerTypeEnum};
 use crate::error::Error;
 use crate::filesystem::{
-    basename, create_directory, delete_repository_by_name,
+    create_directory, delete_repository_by_name,
     delete_template_by_path, get_templates_directory,
     get_repositories_map, get_templates_map, sanitize_path
 };
 use crate::installers::{GitInstaller, LocalInstaller, Installer};
 use crate::managers::{Manager, RepositoryManager, TemplateManager};
-use crate::templates::{Config, Handler, is_correct_template_list, get_template_configs};
+use crate::templates::{Builtins, Config, Generators, Handler, is_correct_template_list, get_template_configs};
+use crate::templates::variables::VariableOptions;
 use crate::terminal::{ask_for_replacing_template, ask_for_input};
 
 pub struct Client {
@@ -38,8 +42,10 @@ impl Client {
                 target,
                 with_override,
                 override_all,
+                strict,
+                seed,
                 templates
-            } => self.init_project(target, with_override, override_all, templates),
+            } => self.init_project(target, with_override, override_all, strict, seed, templates),
             Command::Install {
                 installer_type,
                 path,
@@ -62,16 +68,30 @@ impl Client {
 
     fn init_project(
         &self,
-        target_directory: &String,
+        target_directory: &str,
         with_override: &Option<String>,
         override_all_flag: &bool,
+        strict_flag: &bool,
+        seed: &Option<u64>,
         templates: &Vec<String>
     ) -> Result<(), Error> {
         is_correct_template_list(templates, &self.templates)?;
         let target = sanitize_path(target_directory);
-        let project_name = basename(&target, '/');
-        let mut configs = get_template_configs(&project_name, templates, &self.templates)?;
+        let mut configs = get_template_configs(&target, templates, &self.templates)?;
+        let builtins = Arc::new(Builtins::default());
+        let generators = Arc::new(Generators::new(*seed));
+        configs
+            .values_mut()
+            .for_each(|config| {
+                config.builtins = builtins.clone();
+                config.generators = generators.clone();
+            });
         self.override_default_configs(with_override, override_all_flag, &mut configs)?;
+        if *strict_flag {
+            configs
+                .values_mut()
+                .for_each(|config| config.json_config.strict = Some(true));
+        }
         let handler = Handler::new();
         handler.init_project(&target, &self.templates, &configs)
     }
@@ -80,7 +100,7 @@ impl Client {
         &self,
         with_override: &Option<String>,
         override_all_flag: &bool,
-        configs: &mut HashMap<String, Box<Config>>,
+        configs: &mut IndexMap<String, Box<Config>>,
     ) -> Result<(), Error> {
         let overridable_configs: Vec<String> = match override_all_flag {
             true => configs.keys().map(|key| key.to_owned()).collect(),
@@ -108,36 +128,27 @@ impl Client {
             println!("HINT: Use the Enter key to replace the value or left the default.");
         }
 
-        overridable_configs
-            .iter()
-            .for_each(|template_name| {
-                let mut config = configs.get(template_name).unwrap().clone();
-                let mut variables = config.json_config.variables.clone().unwrap_or_default();
-
-                let mut user_data = HashMap::new();
-                variables
-                    .iter()
-                    .for_each(|(variable_name, default_value)| {
-                        let used_value = match ask_for_input(template_name, variable_name, default_value) {
-                            Some(input) => input,
-                            None => default_value.to_owned(),
-                        };
-
-                        user_data.insert(variable_name.to_owned(), used_value);
-                    });
-
-                if !user_data.is_empty() {
-                    variables.extend(user_data);
-                    config.json_config.variables = Some(variables);
-                    configs.insert(template_name.to_owned(), config);
+        for template_name in overridable_configs.iter() {
+            let config = configs.get_mut(template_name).unwrap();
+            let variables = config.json_config.variables.clone().unwrap_or_default();
+
+            for (variable_name, declaration) in variables.iter() {
+                // Skip the variables which are irrelevant for the previous answers
+                if !config.is_variable_enabled(variable_name) {
+                    continue
                 }
-            });
 
-        configs
-            .iter_mut()
-            .for_each(|(_template_name, config)| {
-                config.refresh_storage_keys();
-            });
+                let options = VariableOptions::from_declaration(declaration).map_err(Error::Other)?;
+                if let Some(input) = ask_for_input(template_name, variable_name, &options) {
+                    config.json_config.set_variable_value(variable_name, input);
+                }
+            }
+        }
+
+        for config in configs.values_mut() {
+            config.validate_variable_values()?;
+            config.refresh_storage_keys()?;
+        }
 
         Ok(())
     }
@@ -145,7 +156,7 @@ impl Client {
     fn install_template(
         &self,
         installer_type: &InstallerTypeEnum,
-        path: &String,
+        path: &str,
         template_name: &Option<String>,
     ) -> Result<(), Error> {
         let worker = self.get_installer_from_enum(installer_type);
@@ -175,7 +186,7 @@ impl Client {
         manager.show_entity_list()
     }
 
-    fn delete_entity_by_name(&self, entity: &EntityTypeEnum, name: &String) -> Result<(), Error> {
+    fn delete_entity_by_name(&self, entity: &EntityTypeEnum, name: &str) -> Result<(), Error> {
         let manager = self.get_manager_from_enum(entity);
         manager.delete_entity(name)
     }
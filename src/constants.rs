@@ -11,3 +11,5 @@ pub static GENERATING_FILES_FROM_TEMPLATES_EMOJI: Emoji<'_, '_> = Emoji("🍪 ",
 pub static INSTALLATION_TASK_HAS_FINISHED_EMOJI: Emoji<'_, '_> = Emoji("🎉 ", "");
 
 pub static OPERATION_HAS_BEEN_COMPLETED_EMOJI: Emoji<'_, '_> = Emoji("✨", ":)");
+
+pub static QUICKPROJ_VERSION: &str = env!("CARGO_PKG_VERSION");
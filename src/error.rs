@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use git2::Error as Git2Error;
 use fs_extra::error::Error as FsExtraCallError;
@@ -24,6 +24,8 @@ quick_error! {
                 -> (err, String::from(source))
             context(source: &PathBuf, err: StdIoError)
                 -> (err, source.to_str().unwrap().to_string())
+            context(source: &Path, err: StdIoError)
+                -> (err, source.display().to_string())
         }
         Git(err: Git2Error) {
             from()
@@ -40,6 +42,11 @@ quick_error! {
             description("serde_json error")
             display("SerdeJson lib error: {}", err)
         }
+        InvalidEncoding(path: String) {
+            description("invalid encoding")
+            display("{}: The template isn't a valid UTF-8 text file. Mark the file as binary \
+                    in the template config to copy it as is.", path)
+        }
         Other(message: String) {
             description(message)
             display("{}", message)
@@ -1,15 +1,19 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
-use std::fs::create_dir_all;
-use std::path::PathBuf;
+use std::fs::{create_dir_all, read};
+use std::path::{Path, PathBuf};
+use std::str::from_utf8;
 
 use dirs::home_dir;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use quick_error::ResultExt;
 use rm_rf::force_remove_all;
 use walkdir::{DirEntry, WalkDir};
 
 use crate::error::Error;
 
-pub static CONFIG_NAME: &'static str = "config.json";
+pub static CONFIG_NAMES: [&str; 3] = ["config.json", "quickproj.yaml", "quickproj.toml"];
+pub static IGNORE_FILE_NAME: &str = ".quickprojignore";
 
 pub fn get_home_directory() -> Result<PathBuf, Error> {
     match home_dir() {
@@ -32,8 +36,8 @@ pub fn get_templates_directory() -> Result<PathBuf, Error> {
 }
 
 pub fn create_directory(path: &PathBuf) -> Result<(), Error> {
-    let operation_result = create_dir_all(path)?;
-    Ok(operation_result)
+    create_dir_all(path)?;
+    Ok(())
 }
 
 pub fn basename<'a>(path: &'a str, sep: char) -> String {
@@ -84,11 +88,18 @@ pub fn get_repositories_map() -> Result<HashMap<String, String>, Error> {
 
 pub fn get_templates_map() -> Result<HashMap<String, String>, Error> {
     let directory = get_templates_directory()?;
+    Ok(find_templates(&directory))
+}
+
+/// Returns the names and the paths of the templates in the directory. The
+/// directories starting with `_` keep the base configs and aren't templates.
+fn find_templates(directory: &PathBuf) -> HashMap<String, String> {
     let mut templates: HashMap<String, String> = HashMap::new();
     WalkDir::new(directory.clone())
         .into_iter()
         .filter_map(|entry| entry.ok())
-        .filter(|entry| is_template_directory(&directory, entry))
+        .filter(|entry| is_template_directory(directory, entry))
+        .filter(|entry| !is_base_directory(entry))
         .for_each(|entry| {
             let template_name = entry
                 .clone()
@@ -105,7 +116,55 @@ pub fn get_templates_map() -> Result<HashMap<String, String>, Error> {
             templates.insert(template_name, path);
         });
 
-    Ok(templates)
+    templates
+}
+
+/// Checks whether the file must be treated as binary: it contains NUL bytes
+/// or isn't a valid UTF-8 text.
+pub fn is_binary_file(path: &Path) -> Result<bool, Error> {
+    let data = read(path).context(path)?;
+    Ok(data.contains(&0) || from_utf8(&data).is_err())
+}
+
+/// Reads the text file. Returns the error, naming the file, if the content
+/// isn't a valid UTF-8 text.
+pub fn read_text_file(path: &Path) -> Result<String, Error> {
+    let data = read(path).context(path)?;
+    String::from_utf8(data).map_err(|_| Error::InvalidEncoding(path.display().to_string()))
+}
+
+/// Reads the glob patterns from the `.quickprojignore` file in the root of the
+/// template. Empty lines and lines starting with `#` are skipped. Patterns
+/// without slashes match files and directories at any depth.
+pub fn get_ignore_patterns(template_directory: &Path) -> Result<GlobSet, Error> {
+    let ignore_file_path = template_directory.join(IGNORE_FILE_NAME);
+    let data = match ignore_file_path.exists() {
+        true => read_text_file(&ignore_file_path)?,
+        false => String::new(),
+    };
+
+    let mut builder = GlobSetBuilder::new();
+    for line in data.lines().map(|line| line.trim()) {
+        if line.is_empty() || line.starts_with('#') {
+            continue
+        }
+
+        let pattern = line.trim_end_matches('/');
+        let pattern = match pattern.trim_start_matches('/') {
+            value if value.contains('/') || pattern.starts_with('/') => value.to_string(),
+            value => format!("**/{}", value),
+        };
+        let glob = GlobBuilder::new(&pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|err| {
+                let message = format!("{}: The `{}` pattern is invalid: {}", ignore_file_path.display(), line, err);
+                Error::Other(message)
+            })?;
+        builder.add(glob);
+    }
+
+    builder.build().map_err(|err| Error::Other(err.to_string()))
 }
 
 pub fn is_hidden_file(entry: &DirEntry) -> bool {
@@ -115,6 +174,13 @@ pub fn is_hidden_file(entry: &DirEntry) -> bool {
          .unwrap_or(false)
 }
 
+pub fn is_base_directory(entry: &DirEntry) -> bool {
+    entry.file_name()
+         .to_str()
+         .map(|s| s.starts_with("_"))
+         .unwrap_or(false)
+}
+
 pub fn is_repository_directory(directory: &PathBuf, entry: &DirEntry) -> bool {
     let entry_path = entry.path();
     let is_directory = entry.file_type().is_dir();
@@ -126,27 +192,58 @@ pub fn is_template_directory(directory: &PathBuf, entry: &DirEntry) -> bool {
     let entry_path = entry.path();
     match entry.file_type().is_dir() && entry_path != directory {
         true => {
-            let config_path = entry_path.join(CONFIG_NAME);
-            config_path.exists()
+            CONFIG_NAMES.iter().any(|config_name| entry_path.join(config_name).exists())
         }
         false => false,
     }
 }
 
-pub fn delete_repository_by_name(repository_name: &String) -> Result<(), Error> {
+/// Returns the path to the configuration file of the template. The template
+/// directory must contain exactly one of the supported configuration files.
+pub fn get_config_path(template_directory: &Path) -> Result<PathBuf, Error> {
+    let config_paths: Vec<PathBuf> = CONFIG_NAMES
+        .iter()
+        .map(|config_name| template_directory.join(config_name))
+        .filter(|config_path| config_path.exists())
+        .collect();
+
+    match config_paths.len() {
+        1 => Ok(config_paths[0].to_owned()),
+        0 => {
+            let message = format!(
+                "{}: The configuration file wasn't found. Expected one of the following files: {}.",
+                template_directory.display(), CONFIG_NAMES.join(", ")
+            );
+            Err(Error::Other(message))
+        },
+        _ => {
+            let config_names: Vec<String> = config_paths
+                .iter()
+                .map(|config_path| basename(&config_path.display().to_string(), '/'))
+                .collect();
+            let message = format!(
+                "{}: The template has multiple configuration files ({}). Please, keep only one of them.",
+                template_directory.display(), config_names.join(", ")
+            );
+            Err(Error::Other(message))
+        }
+    }
+}
+
+pub fn delete_repository_by_name(repository_name: &str) -> Result<(), Error> {
     let templates_directory = get_templates_directory()?;
     let repository_path = templates_directory.join(repository_name);
-    let operation_result = force_remove_all(repository_path)?;
-    Ok(operation_result)
+    force_remove_all(repository_path)?;
+    Ok(())
 }
 
 pub fn delete_template_by_path(template_path: &String) -> Result<(), Error> {
-    let operation_result = force_remove_all(template_path)?;
-    Ok(operation_result)
+    force_remove_all(template_path)?;
+    Ok(())
 }
 
-pub fn sanitize_path(path: &String) -> String {
-    let mut sanitized_path = path.clone().trim().to_string();
+pub fn sanitize_path(path: &str) -> String {
+    let mut sanitized_path = path.trim().to_string();
 
     if sanitized_path.ends_with('/') {
         sanitized_path.pop();
@@ -154,3 +251,54 @@ pub fn sanitize_path(path: &String) -> String {
 
     sanitized_path
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_base_directories_arent_templates() {
+        let directory = tempfile::tempdir().unwrap();
+        for name in ["api", "_base", "repository/web", "repository/_shared"] {
+            fs::create_dir_all(directory.path().join(name)).unwrap();
+            fs::write(directory.path().join(name).join("config.json"), "{}").unwrap();
+        }
+
+        let mut names: Vec<String> = find_templates(&directory.path().to_path_buf()).into_keys().collect();
+        names.sort();
+        assert_eq!(names, vec!["api", "web"]);
+    }
+
+    #[test]
+    fn test_ignore_patterns() {
+        let directory = tempfile::tempdir().unwrap();
+        let data = "# build artifacts\n/build\nlogs/\n\n*.log\ndocs/*.md\n";
+        fs::write(directory.path().join(IGNORE_FILE_NAME), data).unwrap();
+
+        let patterns = get_ignore_patterns(directory.path()).unwrap();
+        // Patterns starting with the slash match only in the root of the template
+        assert!(patterns.is_match("build"));
+        assert!(!patterns.is_match("src/build"));
+        // Patterns without slashes match at any depth
+        assert!(patterns.is_match("logs"));
+        assert!(patterns.is_match("src/logs"));
+        assert!(patterns.is_match("src/app.log"));
+        // Wildcards don't match the path separators
+        assert!(patterns.is_match("docs/index.md"));
+        assert!(!patterns.is_match("docs/api/index.md"));
+        assert!(!patterns.is_match("README.md"));
+        assert!(!patterns.is_match("# build artifacts"));
+    }
+
+    #[test]
+    fn test_invalid_ignore_patterns() {
+        let directory = tempfile::tempdir().unwrap();
+        assert!(get_ignore_patterns(directory.path()).unwrap().is_empty());
+
+        fs::write(directory.path().join(IGNORE_FILE_NAME), "src/[a-z\n").unwrap();
+        let message = get_ignore_patterns(directory.path()).unwrap_err().to_string();
+        assert!(message.contains(".quickprojignore: The `src/[a-z` pattern is invalid: "));
+    }
+}
@@ -60,8 +60,8 @@ impl Installer for GitInstaller {
         GitInstaller {}
     }
 
-    fn get_template_name(&self, url: &String) -> Result<String, Error> {
-        let mut repository_name = url.clone();
+    fn get_template_name(&self, url: &str) -> Result<String, Error> {
+        let mut repository_name = url.to_string();
         if repository_name.ends_with('/') {
             repository_name.pop();
         }
@@ -72,10 +72,10 @@ impl Installer for GitInstaller {
             }
         }
 
-        Ok(basename(&repository_name.as_str(), '/'))
+        Ok(basename(repository_name.as_str(), '/'))
     }
 
-    fn install(&self, url: &String, template_name: &String) -> Result<(), Error> {
+    fn install(&self, url: &str, template_name: &str) -> Result<(), Error> {
         let templates_folder = get_templates_directory()?;
         let destination = templates_folder.join(template_name);
         let started = Instant::now();
@@ -90,7 +90,7 @@ impl Installer for GitInstaller {
         cb.transfer_progress(|stats| {
             let mut state = state.borrow_mut();
             state.progress = Some(stats.to_owned());
-            self.refresh_download_progress_bar(&download_pb, &mut *state);
+            self.refresh_download_progress_bar(&download_pb, &mut state);
             true
         });
 
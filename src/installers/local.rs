@@ -48,8 +48,8 @@ impl Installer for LocalInstaller {
         LocalInstaller {}
     }
 
-    fn get_template_name(&self, path: &String) -> Result<String, Error> {
-        let mut template_name = path.clone();
+    fn get_template_name(&self, path: &str) -> Result<String, Error> {
+        let mut template_name = path.to_string();
         if template_name.ends_with('/') {
             template_name.pop();
         }
@@ -57,7 +57,7 @@ impl Installer for LocalInstaller {
         Ok(basename(&template_name, '/'))
     }
 
-    fn install(&self, source: &String, template_name: &String) -> Result<(), Error> {
+    fn install(&self, source: &str, template_name: &str) -> Result<(), Error> {
         let templates_folder = get_templates_directory()?;
         let destination = templates_folder.join(template_name);
         let started = Instant::now();
@@ -69,7 +69,7 @@ impl Installer for LocalInstaller {
             self.refresh_copy_progress_bar(&copy_pb, &state);
             fs_extra::dir::TransitProcessResult::OverwriteAll
         };
-        copy_with_progress(&source, &destination, &options, handle)?;
+        copy_with_progress(source, &destination, &options, handle)?;
 
         println!(
             "{} Done in {}",
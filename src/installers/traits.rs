@@ -2,6 +2,6 @@ use crate::error::Error;
 
 pub trait Installer {
     fn new() -> Self where Self: Sized;
-    fn get_template_name(&self, path: &String) -> Result<String, Error>;
-    fn install(&self, path: &String, template_name: &String) -> Result<(), Error>;
+    fn get_template_name(&self, path: &str) -> Result<String, Error>;
+    fn install(&self, path: &str, template_name: &str) -> Result<(), Error>;
 }
@@ -35,7 +35,7 @@ impl Manager for RepositoryManager {
         Ok(())
     }
 
-    fn delete_entity(&self, name: &String) -> Result<(), Error> {
+    fn delete_entity(&self, name: &str) -> Result<(), Error> {
         if self.repositories.is_empty() {
             println!("The templates folder is empty. Please, install templates first.");
             return Ok(())
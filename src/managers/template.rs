@@ -1,17 +1,42 @@
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::time::Instant;
 
 use indicatif::HumanDuration;
 
 use crate::constants::OPERATION_HAS_BEEN_COMPLETED_EMOJI;
 use crate::error::Error;
-use crate::filesystem::delete_template_by_path;
+use crate::filesystem::{delete_template_by_path, get_config_path};
 use crate::managers::traits::Manager;
+use crate::templates::config::JsonConfig;
 
 pub struct TemplateManager {
     templates: HashMap<String, String>
 }
 
+impl TemplateManager {
+    /// Returns the name of the template with the details from the
+    /// config[meta] space. Templates with the broken configs are listed too,
+    /// so that they can be found and deleted.
+    fn describe_template(&self, template_name: &String) -> String {
+        let template_path = PathBuf::from(self.templates.get(template_name).unwrap());
+        let json_config = get_config_path(&template_path)
+            .and_then(|config_path| JsonConfig::read_file(&config_path.display().to_string()));
+
+        let meta = match json_config {
+            Ok(json_config) => match json_config.meta {
+                Some(meta) => meta,
+                None => return template_name.to_owned(),
+            },
+            Err(_) => return format!("{} (the configuration can't be read)", template_name),
+        };
+
+        let mut lines = vec![format!("{} - {}", template_name, meta.get_title(template_name))];
+        lines.extend(meta.get_details().iter().map(|line| format!("  {}", line)));
+        lines.join("\n  ")
+    }
+}
+
 impl Manager for TemplateManager {
     fn new(templates: &HashMap<String, String>) -> Self where Self: Sized {
         TemplateManager {
@@ -25,9 +50,12 @@ impl Manager for TemplateManager {
             return Ok(())
         }
 
-        let templates = self.templates
-            .keys()
-            .map(|key| &**key)
+        let mut template_names = self.templates.keys().collect::<Vec<_>>();
+        template_names.sort();
+
+        let templates = template_names
+            .iter()
+            .map(|template_name| self.describe_template(template_name))
             .collect::<Vec<_>>()
             .join("\n  ");
 
@@ -35,7 +63,7 @@ impl Manager for TemplateManager {
         Ok(())
     }
 
-    fn delete_entity(&self, template_name: &String) -> Result<(), Error> {
+    fn delete_entity(&self, template_name: &str) -> Result<(), Error> {
         if self.templates.is_empty() {
             println!("The templates folder is empty. Please, install templates first.");
             return Ok(())
@@ -0,0 +1,102 @@
+use std::env::{consts, current_dir, var};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use chrono::{Local, SecondsFormat};
+use git2::Config as GitConfig;
+use serde_json::{json, Map as SerdeMap, Value as SerdeValue};
+
+use crate::constants::QUICKPROJ_VERSION;
+
+/// Variables available in each template without declaring them in the
+/// config. The date, the git identity and other values that don't depend on
+/// the template are read once, so that all templates used in the project
+/// initialization get the same values.
+#[derive(Debug, Default)]
+pub struct Builtins {
+    shared_variables: OnceLock<SerdeMap<String, SerdeValue>>,
+}
+
+impl Builtins {
+    /// Returns the current date, the git identity, the platform, the target
+    /// directory, the quickproj version and the whitelisted environment variables.
+    /// The git identity and the environment variables are skipped when not set.
+    pub fn get_variables(
+        &self,
+        target_directory_path: &Path,
+        environment: &[String],
+    ) -> SerdeMap<String, SerdeValue> {
+        let mut variables = self.shared_variables.get_or_init(get_shared_variables).clone();
+        variables.insert(
+            "target_directory".to_string(),
+            json!(get_absolute_path(target_directory_path).display().to_string())
+        );
+
+        let environment_variables: SerdeMap<String, SerdeValue> = environment
+            .iter()
+            .filter_map(|name| var(name).ok().map(|value| (name.to_owned(), json!(value))))
+            .collect();
+        variables.insert("env".to_string(), json!(environment_variables));
+
+        variables
+    }
+}
+
+fn get_shared_variables() -> SerdeMap<String, SerdeValue> {
+    let mut variables = SerdeMap::new();
+    let now = Local::now();
+    variables.insert("year".to_string(), json!(now.format("%Y").to_string()));
+    variables.insert("date".to_string(), json!(now.format("%Y-%m-%d").to_string()));
+    variables.insert("timestamp".to_string(), json!(now.to_rfc3339_opts(SecondsFormat::Secs, false)));
+
+    if let Ok(git_config) = GitConfig::open_default() {
+        for (key, git_key) in [("git_user_name", "user.name"), ("git_user_email", "user.email")] {
+            if let Ok(value) = git_config.get_string(git_key) {
+                variables.insert(key.to_string(), json!(value));
+            }
+        }
+    }
+
+    variables.insert("os".to_string(), json!(consts::OS));
+    variables.insert("arch".to_string(), json!(consts::ARCH));
+    variables.insert("quickproj_version".to_string(), json!(QUICKPROJ_VERSION));
+    variables
+}
+
+/// Resolves the path relatively to the current directory, because the target
+/// directory can be not created yet.
+fn get_absolute_path(path: &Path) -> PathBuf {
+    match path.is_absolute() {
+        true => path.to_owned(),
+        false => {
+            current_dir()
+                .map(|current_directory| current_directory.join(path))
+                .unwrap_or(path.to_owned())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use super::Builtins;
+
+    #[test]
+    fn test_shared_variables_are_read_once() {
+        let builtins = Builtins::default();
+        let first = builtins.get_variables(Path::new("/tmp/first"), &[]);
+        sleep(Duration::from_millis(1100));
+        let second = builtins.get_variables(Path::new("/tmp/second"), &["PATH".to_string()]);
+
+        assert_eq!(first["timestamp"], second["timestamp"]);
+        assert_eq!(first["target_directory"], json!("/tmp/first"));
+        assert_eq!(second["target_directory"], json!("/tmp/second"));
+        assert_eq!(first["env"], json!({}));
+        assert!(second["env"].get("PATH").is_some());
+    }
+}
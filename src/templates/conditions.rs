@@ -0,0 +1,418 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+use serde_json::{json, Value as SerdeValue};
+
+use crate::templates::renderer::{get_context_value, is_truthy_value, stringify_value};
+
+/// Boolean expression, used in the `{% if %}` tags and in the `when` keys of
+/// the variables, e.g. `use_docker and database != 'none'`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Value(Operand),
+    Compare(Operand, Comparison, Operand),
+    Not(Box<Condition>),
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+}
+
+/// Variable from the context or the literal value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Variable(String),
+    Literal(SerdeValue),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    In,
+    NotIn,
+}
+
+/// Lexical piece of the condition.
+#[derive(Debug, Clone, PartialEq)]
+enum ConditionToken {
+    Variable(String),
+    Literal(SerdeValue),
+    Equal,
+    NotEqual,
+    And,
+    Or,
+    Not,
+    In,
+    OpenParenthesis,
+    CloseParenthesis,
+}
+
+/// Recursive descent parser with the following grammar:
+///   or_expression  := and_expression ("or" and_expression)*
+///   and_expression := not_expression ("and" not_expression)*
+///   not_expression := "not" not_expression | primary
+///   primary        := "(" or_expression ")" | operand (comparison operand)?
+///   comparison     := "==" | "!=" | "in" | "not" "in"
+struct ConditionParser {
+    tokens: Vec<ConditionToken>,
+    position: usize,
+}
+
+impl Condition {
+    /// Parses the expression. Errors describe the invalid part of the expression.
+    pub fn parse(expression: &str) -> Result<Condition, String> {
+        let tokens = tokenize(expression)?;
+        if tokens.is_empty() {
+            return Err(String::from("the condition is empty"))
+        }
+
+        let mut parser = ConditionParser { tokens, position: 0 };
+        let condition = parser.parse_or_expression()?;
+        match parser.peek() {
+            Some(token) => Err(format!("unexpected {}", describe_token(token))),
+            None => Ok(condition),
+        }
+    }
+
+    /// Evaluates the condition against the context. Missing variables are
+    /// treated as false and aren't equal to any value.
+    pub fn evaluate(&self, context: &SerdeValue) -> bool {
+        match self {
+            Condition::Value(operand) => {
+                operand.resolve(context)
+                    .map(|value| is_truthy_value(&value))
+                    .unwrap_or(false)
+            },
+            Condition::Compare(left, comparison, right) => {
+                let left_value = left.resolve(context);
+                let right_value = right.resolve(context);
+                match comparison {
+                    Comparison::Equal => are_equal_values(&left_value, &right_value),
+                    Comparison::NotEqual => !are_equal_values(&left_value, &right_value),
+                    Comparison::In => is_contained_value(&left_value, &right_value),
+                    Comparison::NotIn => !is_contained_value(&left_value, &right_value),
+                }
+            },
+            Condition::Not(condition) => !condition.evaluate(context),
+            Condition::And(left, right) => left.evaluate(context) && right.evaluate(context),
+            Condition::Or(left, right) => left.evaluate(context) || right.evaluate(context),
+        }
+    }
+
+    /// Returns names of all variables used in the condition.
+    pub fn get_variable_names(&self) -> Vec<String> {
+        match self {
+            Condition::Value(operand) => operand.get_variable_name().into_iter().collect(),
+            Condition::Compare(left, _, right) => {
+                left.get_variable_name()
+                    .into_iter()
+                    .chain(right.get_variable_name())
+                    .collect()
+            },
+            Condition::Not(condition) => condition.get_variable_names(),
+            Condition::And(left, right) | Condition::Or(left, right) => {
+                let mut names = left.get_variable_names();
+                names.extend(right.get_variable_names());
+                names
+            },
+        }
+    }
+}
+
+impl Operand {
+    fn resolve(&self, context: &SerdeValue) -> Option<SerdeValue> {
+        match self {
+            Operand::Variable(name) => get_context_value(context, name).cloned(),
+            Operand::Literal(value) => Some(value.clone()),
+        }
+    }
+
+    fn get_variable_name(&self) -> Option<String> {
+        match self {
+            Operand::Variable(name) => Some(name.to_owned()),
+            Operand::Literal(_) => None,
+        }
+    }
+}
+
+impl ConditionParser {
+    fn peek(&self) -> Option<&ConditionToken> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<ConditionToken> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn parse_or_expression(&mut self) -> Result<Condition, String> {
+        let mut condition = self.parse_and_expression()?;
+        while self.peek() == Some(&ConditionToken::Or) {
+            self.next();
+            let right = self.parse_and_expression()?;
+            condition = Condition::Or(Box::new(condition), Box::new(right));
+        }
+        Ok(condition)
+    }
+
+    fn parse_and_expression(&mut self) -> Result<Condition, String> {
+        let mut condition = self.parse_not_expression()?;
+        while self.peek() == Some(&ConditionToken::And) {
+            self.next();
+            let right = self.parse_not_expression()?;
+            condition = Condition::And(Box::new(condition), Box::new(right));
+        }
+        Ok(condition)
+    }
+
+    fn parse_not_expression(&mut self) -> Result<Condition, String> {
+        match self.peek() {
+            Some(ConditionToken::Not) => {
+                self.next();
+                Ok(Condition::Not(Box::new(self.parse_not_expression()?)))
+            },
+            _ => self.parse_primary(),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Condition, String> {
+        if self.peek() == Some(&ConditionToken::OpenParenthesis) {
+            self.next();
+            let condition = self.parse_or_expression()?;
+            return match self.next() {
+                Some(ConditionToken::CloseParenthesis) => Ok(condition),
+                Some(token) => Err(format!("expected `)`, but got {}", describe_token(&token))),
+                None => Err(String::from("expected `)` at the end of the condition")),
+            }
+        }
+
+        let left = self.parse_operand()?;
+        let comparison = match (self.peek(), self.tokens.get(self.position + 1)) {
+            (Some(ConditionToken::Equal), _) => Comparison::Equal,
+            (Some(ConditionToken::NotEqual), _) => Comparison::NotEqual,
+            (Some(ConditionToken::In), _) => Comparison::In,
+            (Some(ConditionToken::Not), Some(ConditionToken::In)) => {
+                self.next();
+                Comparison::NotIn
+            },
+            _ => return Ok(Condition::Value(left)),
+        };
+        self.next();
+
+        let right = self.parse_operand()?;
+        Ok(Condition::Compare(left, comparison, right))
+    }
+
+    fn parse_operand(&mut self) -> Result<Operand, String> {
+        match self.next() {
+            Some(ConditionToken::Variable(name)) => Ok(Operand::Variable(name)),
+            Some(ConditionToken::Literal(value)) => Ok(Operand::Literal(value)),
+            Some(token) => Err(format!("expected a variable or a value, but got {}", describe_token(&token))),
+            None => Err(String::from("unexpected end of the condition")),
+        }
+    }
+}
+
+/// Splits the expression into variables, literals, operators and parentheses.
+fn tokenize(expression: &str) -> Result<Vec<ConditionToken>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().peekable();
+    while let Some(&character) = chars.peek() {
+        match character {
+            _ if character.is_whitespace() => {
+                chars.next();
+            },
+            '(' | ')' => {
+                chars.next();
+                tokens.push(match character {
+                    '(' => ConditionToken::OpenParenthesis,
+                    _ => ConditionToken::CloseParenthesis,
+                });
+            },
+            '=' | '!' => {
+                chars.next();
+                if chars.next() != Some('=') {
+                    return Err(format!("unexpected `{}` character, expected `==` or `!=`", character))
+                }
+                tokens.push(match character {
+                    '=' => ConditionToken::Equal,
+                    _ => ConditionToken::NotEqual,
+                });
+            },
+            '\'' | '"' => {
+                chars.next();
+                let value = read_quoted_string(&mut chars, character)?;
+                tokens.push(ConditionToken::Literal(json!(value)));
+            },
+            _ if is_word_character(character) => {
+                let mut word = String::new();
+                while let Some(&character) = chars.peek() {
+                    if !is_word_character(character) {
+                        break
+                    }
+                    word.push(character);
+                    chars.next();
+                }
+                tokens.push(get_word_token(&word));
+            },
+            _ => return Err(format!("unexpected `{}` character", character)),
+        }
+    }
+    Ok(tokens)
+}
+
+fn read_quoted_string(chars: &mut Peekable<Chars>, quote: char) -> Result<String, String> {
+    let mut value = String::new();
+    for character in chars {
+        if character == quote {
+            return Ok(value)
+        }
+        value.push(character);
+    }
+    Err(format!("the `{}{}` string isn't closed", quote, value))
+}
+
+fn is_word_character(character: char) -> bool {
+    character.is_alphanumeric() || character == '_' || character == '.' || character == '-'
+}
+
+fn get_word_token(word: &str) -> ConditionToken {
+    match word {
+        "and" => ConditionToken::And,
+        "or" => ConditionToken::Or,
+        "not" => ConditionToken::Not,
+        "in" => ConditionToken::In,
+        "true" => ConditionToken::Literal(json!(true)),
+        "false" => ConditionToken::Literal(json!(false)),
+        _ => {
+            if let Ok(number) = word.parse::<i64>() {
+                return ConditionToken::Literal(json!(number))
+            }
+            match word.parse::<f64>() {
+                Ok(number) if word.starts_with(|c: char| c.is_ascii_digit() || c == '-') => {
+                    ConditionToken::Literal(json!(number))
+                },
+                _ => ConditionToken::Variable(word.to_string()),
+            }
+        },
+    }
+}
+
+fn describe_token(token: &ConditionToken) -> String {
+    match token {
+        ConditionToken::Variable(name) => format!("the `{}` variable", name),
+        ConditionToken::Literal(value) => format!("the `{}` value", value),
+        ConditionToken::Equal => String::from("`==`"),
+        ConditionToken::NotEqual => String::from("`!=`"),
+        ConditionToken::And => String::from("`and`"),
+        ConditionToken::Or => String::from("`or`"),
+        ConditionToken::Not => String::from("`not`"),
+        ConditionToken::In => String::from("`in`"),
+        ConditionToken::OpenParenthesis => String::from("`(`"),
+        ConditionToken::CloseParenthesis => String::from("`)`"),
+    }
+}
+
+/// Compares two values. Strings are compared with numbers and booleans by
+/// their text representation, because values from the paths are strings.
+fn are_equal_values(left: &Option<SerdeValue>, right: &Option<SerdeValue>) -> bool {
+    match (left, right) {
+        (Some(left), Some(right)) if left == right => true,
+        (Some(left), Some(right)) => {
+            match (stringify_value(left), stringify_value(right)) {
+                (Some(left), Some(right)) => left == right,
+                _ => false,
+            }
+        },
+        _ => false,
+    }
+}
+
+/// Checks whether the array contains the item, the string contains the
+/// substring or the object contains the key.
+fn is_contained_value(item: &Option<SerdeValue>, container: &Option<SerdeValue>) -> bool {
+    match container {
+        Some(SerdeValue::Array(values)) => {
+            values.iter().any(|value| are_equal_values(item, &Some(value.clone())))
+        },
+        Some(SerdeValue::String(data)) => {
+            item.as_ref()
+                .and_then(stringify_value)
+                .map(|item| data.contains(&item))
+                .unwrap_or(false)
+        },
+        Some(SerdeValue::Object(map)) => {
+            item.as_ref()
+                .and_then(stringify_value)
+                .map(|key| map.contains_key(&key))
+                .unwrap_or(false)
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn evaluate(expression: &str, context: &SerdeValue) -> bool {
+        Condition::parse(expression).unwrap().evaluate(context)
+    }
+
+    #[test]
+    fn test_and_binds_tighter_than_or() {
+        let condition = Condition::parse("a or b and c").unwrap();
+        let expected = Condition::Or(
+            Box::new(Condition::Value(Operand::Variable("a".to_string()))),
+            Box::new(Condition::And(
+                Box::new(Condition::Value(Operand::Variable("b".to_string()))),
+                Box::new(Condition::Value(Operand::Variable("c".to_string()))),
+            )),
+        );
+        assert_eq!(condition, expected);
+
+        let context = json!({"a": true, "b": false, "c": false});
+        assert!(evaluate("a or b and c", &context));
+        assert!(!evaluate("(a or b) and c", &context));
+    }
+
+    #[test]
+    fn test_not_binds_tighter_than_and() {
+        let context = json!({"a": false, "b": false});
+        assert!(!evaluate("not a and b", &context));
+        assert!(evaluate("not (a and b)", &context));
+        assert!(evaluate("not not not a", &context));
+    }
+
+    #[test]
+    fn test_comparisons() {
+        let context = json!({"db": "postgres", "port": 5432, "envs": ["dev", "prod"], "env": {"CI": "true"}});
+        assert!(evaluate("db == 'postgres' and port == 5432", &context));
+        assert!(evaluate("port == \"5432\"", &context));
+        assert!(evaluate("db != \"mysql\"", &context));
+        assert!(evaluate("'prod' in envs and 'qa' not in envs", &context));
+        assert!(evaluate("'gres' in db", &context));
+        assert!(evaluate("env.CI == true", &context));
+        assert!(!evaluate("missing == missing", &context));
+        assert!(evaluate("not missing", &context));
+    }
+
+    #[test]
+    fn test_variable_names() {
+        let condition = Condition::parse("a == 'x' or not (b in c)").unwrap();
+        assert_eq!(condition.get_variable_names(), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_invalid_conditions() {
+        assert_eq!(Condition::parse("").unwrap_err(), "the condition is empty");
+        assert_eq!(Condition::parse("(a or b").unwrap_err(), "expected `)` at the end of the condition");
+        assert_eq!(Condition::parse("a b").unwrap_err(), "unexpected the `b` variable");
+        assert_eq!(Condition::parse("a ==").unwrap_err(), "unexpected end of the condition");
+        assert_eq!(Condition::parse("a = b").unwrap_err(), "unexpected `=` character, expected `==` or `!=`");
+        assert_eq!(Condition::parse("a == 'b").unwrap_err(), "the `'b` string isn't closed");
+        assert!(Condition::parse("a and or b").unwrap_err().contains("`or`"));
+    }
+}
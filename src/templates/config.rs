@@ -2,42 +2,114 @@ use std::collections::HashMap;
 use std::default::Default;
 use std::fs::File;
 use std::io::prelude::Read;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use indexmap::IndexMap;
+use quick_error::ResultExt;
 use regex::Regex;
+use semver::Version;
 use serde::Deserialize;
 use serde_json::{json, Map as SerdeMap, Value as SerdeValue};
 use lazy_static::lazy_static;
 
+use crate::constants::QUICKPROJ_VERSION;
 use crate::error::Error;
-use crate::filesystem::CONFIG_NAME;
+use crate::filesystem::{basename, get_config_path};
+use crate::templates::builtins::Builtins;
+use crate::templates::engine::{AVAILABLE_ENGINES, DEFAULT_ENGINE_NAME};
+use crate::templates::generators::{Generators, GENERATOR_PLACEHOLDER_REGEX};
+use crate::templates::renderer::stringify_value;
+use crate::templates::variables::VariableOptions;
 
 lazy_static! {
     static ref DEFAULT_TARGET_DIRECTORY: String = String::from(".");
-    static ref REFERENCE_VARIABLE_REGEX: Regex = Regex::new(r"\{\{\s*\bVars.(?P<name>[\w\d_-]*)\b\s*}}").unwrap();
+    static ref REFERENCE_VARIABLE_REGEX: Regex = Regex::new(r"\{\{\s*\b(?P<namespace>Vars|Storage)\.(?P<name>[\w-]+)\s*}}").unwrap();
+    static ref PLACEHOLDER_REGEX: Regex = Regex::new(r"\{\{.*?}}").unwrap();
 }
 
 #[derive(Debug, Clone)]
 pub struct Config {
     pub project_name: Option<String>,
     pub template_name: Option<String>,
+    pub target_directory: Option<PathBuf>,
+    pub builtins: Arc<Builtins>,
+    pub generators: Arc<Generators>,
     pub json_config: JsonConfig,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct JsonConfig {
+    pub meta: Option<MetaConfig>,
+    pub requires: Option<Vec<String>>,
     pub files: FilesConfig,
-    pub variables: Option<HashMap<String, SerdeValue>>,
+    pub variables: Option<SerdeMap<String, SerdeValue>>,
     pub scripts: Option<ScriptsConfig>,
     pub storage: Option<StorageConfig>,
+    pub strict: Option<bool>,
+    pub delimiters: Option<DelimitersConfig>,
+    pub environment: Option<Vec<String>>,
+    pub engine: Option<String>,
+}
+
+/// Optional description of the template in the config[meta] space.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MetaConfig {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub version: Option<String>,
+    pub author: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub homepage: Option<String>,
+    pub min_quickproj_version: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct FilesConfig {
-    pub sources: Vec<HashMap<String, String>>,
+    pub sources: Vec<SourceConfig>,
     pub generated: Option<Vec<String>>,
     pub directories: Option<Vec<String>>,
-    pub templates: Option<HashMap<String, String>>
+    pub templates: Option<HashMap<String, TemplateEntry>>
+}
+
+/// Record in the config[files][sources] space. Files that match any of the
+/// `render` glob patterns are rendered with the template context instead of
+/// copying them as is, unless they match any of the `binary` glob patterns.
+/// The `include` and `exclude` glob patterns filter the copied files.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SourceConfig {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub render: Option<Vec<String>>,
+    pub binary: Option<Vec<String>>,
+    pub include: Option<Vec<String>>,
+    pub exclude: Option<Vec<String>>,
+}
+
+/// Record in the config[files][templates] space. Can be specified as the path
+/// to the template or as the object with the path and additional options.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum TemplateEntry {
+    Path(String),
+    Detailed(TemplateOptions),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TemplateOptions {
+    pub path: String,
+    pub render: Option<bool>,
+    pub binary: Option<bool>,
+}
+
+/// Start and end delimiters for the placeholders, the tags and the comments in templates.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DelimitersConfig {
+    pub variable: Option<(String, String)>,
+    pub block: Option<(String, String)>,
+    pub comment: Option<(String, String)>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -55,6 +127,9 @@ impl Config {
         Config {
             project_name: None,
             template_name: None,
+            target_directory: None,
+            builtins: Arc::new(Builtins::default()),
+            generators: Arc::new(Generators::default()),
             json_config
         }
     }
@@ -69,16 +144,20 @@ impl Config {
         self
     }
 
-    pub fn get_source_entries(&self, template_path: &PathBuf) -> Vec<HashMap<String, String>> {
+    pub fn with_target_directory(mut self, target_directory: &PathBuf) -> Self {
+        self.target_directory = Some(target_directory.to_owned());
+        self
+    }
+
+    pub fn get_source_entries(&self, template_path: &Path) -> Vec<SourceConfig> {
         self.json_config.files.sources.clone()
             .iter()
             .map(|entry| {
-                let from_path = entry.get("from").unwrap();
+                let from_path = entry.get_from();
                 let updated_from_path = match from_path.starts_with(".") {
-                    true => template_path.clone().to_str().unwrap().to_string(),
+                    true => template_path.to_str().unwrap().to_string(),
                     false => {
                         template_path
-                            .clone()
                             .join(PathBuf::from(from_path))
                             .into_os_string()
                             .into_string()
@@ -86,29 +165,53 @@ impl Config {
                     }
                 };
 
-                let to_path = entry
-                    .get("to")
+                let to_path = entry.to
+                    .as_ref()
                     .unwrap_or(&DEFAULT_TARGET_DIRECTORY);
                 let updated_to_path = match to_path.starts_with("./") {
                     true => to_path.replacen("./", "", 1),
                     false => to_path.clone(),
                 };
 
-                let mut updated_entry = HashMap::new();
-                updated_entry.insert("from".to_string(), updated_from_path.to_string());
-                updated_entry.insert("to".to_string(), updated_to_path.to_string());
-
-                entry.clone()
-                    .into_iter()
-                    .chain(updated_entry.clone())
-                    .collect()
+                SourceConfig {
+                    from: Some(updated_from_path),
+                    to: Some(updated_to_path),
+                    render: entry.render.clone(),
+                    binary: entry.binary.clone(),
+                    include: entry.include.clone(),
+                    exclude: entry.exclude.clone(),
+                }
             })
             .collect()
     }
 
-    pub fn refresh_storage_keys(&mut self) {
-        self.json_config.replace_storage_references();
+    pub fn get_delimiters(&self) -> DelimitersConfig {
+        self.json_config.delimiters.clone().unwrap_or_default()
+    }
+
+    pub fn get_engine(&self) -> String {
+        self.json_config.engine.clone().unwrap_or(DEFAULT_ENGINE_NAME.to_string())
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.json_config.strict.unwrap_or(false)
+    }
+
+    /// Evaluates config[storage][variables] with the final values of the
+    /// variables and makes them available in the template context.
+    pub fn refresh_storage_keys(&mut self) -> Result<(), Error> {
+        let mut context = SerdeMap::new();
+        self.add_config_definition_to_context(&mut context);
+        let variables: SerdeMap<String, SerdeValue> = self.json_config
+            .get_enabled_variables(&context)
+            .into_iter()
+            .filter_map(|(key, options)| options.get_value().map(|value| (key, value)))
+            .collect();
+
+        self.json_config.generate_storage_values(&self.generators)?;
+        self.json_config.replace_storage_references(&variables)?;
         self.json_config.merge_storage_with_variables();
+        Ok(())
     }
 
     pub fn get_template_context(&self) -> Box<SerdeValue> {
@@ -119,6 +222,9 @@ impl Config {
     }
 
     fn add_config_definition_to_context(&self, context: &mut SerdeMap<String, SerdeValue>) {
+        let target_directory = self.target_directory.clone().unwrap_or_default();
+        let environment = self.json_config.environment.clone().unwrap_or_default();
+        context.extend(self.builtins.get_variables(&target_directory, &environment));
         context.insert(
             "project_name".to_string(),
             SerdeValue::String(self.project_name.clone().unwrap_or("unknown".to_string()))
@@ -130,49 +236,114 @@ impl Config {
     }
 
     fn add_variables_to_context(&self, context: &mut SerdeMap<String, SerdeValue>) {
-        self.json_config.variables.clone().unwrap_or_default()
-            .iter()
-            .map(|(key, value)| self.inject_value_in_context(key, value, context))
-            .collect()
+        for (key, options) in self.json_config.get_enabled_variables(context) {
+            self.inject_value_in_context(&key, options.get_value(), context)
+        }
     }
 
     fn inject_value_in_context(
         &self,
         key: &String,
-        value: &SerdeValue,
+        value: Option<SerdeValue>,
         context: &mut SerdeMap<String, SerdeValue>
     ) {
         match value {
-            SerdeValue::String(_) => {
-                context.insert(key.to_string(), value.clone());
-                ()
-            },
-            SerdeValue::Array(_) => {
-                context.insert(key.to_string(), value.clone());
-                ()
+            Some(value @ SerdeValue::String(_)) |
+            Some(value @ SerdeValue::Array(_)) |
+            Some(value @ SerdeValue::Bool(_)) |
+            Some(value @ SerdeValue::Number(_)) => {
+                context.insert(key.to_string(), value);
             },
             _ => {}
         }
     }
+
+    /// Checks whether the `when` condition of the variable is satisfied by
+    /// the current values of the previously declared variables.
+    pub fn is_variable_enabled(&self, name: &str) -> bool {
+        let mut context = SerdeMap::new();
+        self.add_config_definition_to_context(&mut context);
+        self.json_config
+            .get_enabled_variables(&context)
+            .iter()
+            .any(|(key, _)| key == name)
+    }
+
+    /// Checks that all required variables have got the values and that the
+    /// values, including the ones which weren't specified by the user,
+    /// satisfy the constraints of the variables. Variables with unsatisfied
+    /// `when` conditions are skipped.
+    pub fn validate_variable_values(&self) -> Result<(), Error> {
+        let template_name = self.template_name.clone().unwrap_or_default();
+        let declarations = self.json_config.variables.clone().unwrap_or_default();
+        let mut context = SerdeMap::new();
+        self.add_config_definition_to_context(&mut context);
+
+        let mut missing_variables: Vec<String> = Vec::new();
+        for (key, options) in self.json_config.get_enabled_variables(&context) {
+            let is_shorthand = declarations.get(&key).map(|declaration| !declaration.is_object()).unwrap_or(true);
+            match options.get_value() {
+                // Values of the shorthand declarations have no constraints
+                Some(_) if is_shorthand => {},
+                Some(value) => {
+                    if let Err(err) = options.validate_value(&value) {
+                        let message = format!(
+                            "The `{}:{}` variable has an invalid value: {}.",
+                            template_name, key, err
+                        );
+                        return Err(Error::Other(message))
+                    }
+                },
+                None => missing_variables.push(format!("`{}`", key)),
+            }
+        }
+
+        match missing_variables.is_empty() {
+            true => Ok(()),
+            false => {
+                let message = format!(
+                    "The `{}` template requires the values for the following variables: {}. \
+                    Please, specify them with the `--override` option.",
+                    template_name, missing_variables.join(", ")
+                );
+                Err(Error::Other(message))
+            }
+        }
+    }
 }
 
 impl JsonConfig {
+    /// Reads and validates the config in the JSON, YAML or TOML format,
+    /// depending on the extension of the file.
     pub fn from_file(path: &String) -> Result<JsonConfig, Error> {
-        let mut data = String::new();
-        File::open(path)
-            .and_then(|mut f| f.read_to_string(&mut data))?;
-
-        let mut json_config: JsonConfig = serde_json::from_str(&data)?;
+        let mut json_config = JsonConfig::read_file(path)?;
         json_config.init_missing_fields();
         json_config.validate(path)?;
         Ok(json_config)
     }
 
+    /// Reads the config as is, without the validation. The config is merged
+    /// with the base config, specified in the `extends` key.
+    pub fn read_file(path: &String) -> Result<JsonConfig, Error> {
+        let data = read_config_value(&PathBuf::from(path), &mut Vec::new())?;
+        serde_json::from_value(data).map_err(|err| Error::Other(format!("{}: {}", path, err)))
+    }
+
     pub fn init_missing_fields(&mut self) {
         self.files.generated = Some(self.files.generated.clone().unwrap_or_default());
         self.files.directories = Some(self.files.directories.clone().unwrap_or_default());
         self.files.templates = Some(self.files.templates.clone().unwrap_or_default());
         self.variables = Some(self.variables.clone().unwrap_or_default());
+        self.strict = Some(self.strict.unwrap_or(false));
+        self.environment = Some(self.environment.clone().unwrap_or_default());
+        self.engine = Some(self.engine.clone().unwrap_or(DEFAULT_ENGINE_NAME.to_string()));
+
+        let default_delimiters = DelimitersConfig::default();
+        let mut delimiters = self.delimiters.clone().unwrap_or_default();
+        delimiters.variable = delimiters.variable.or(default_delimiters.variable);
+        delimiters.block = delimiters.block.or(default_delimiters.block);
+        delimiters.comment = delimiters.comment.or(default_delimiters.comment);
+        self.delimiters = Some(delimiters);
 
         if self.scripts.is_none() {
             self.scripts = Some(ScriptsConfig::default());
@@ -183,26 +354,75 @@ impl JsonConfig {
         }
     }
 
-    pub fn replace_storage_references(&mut self) {
+    /// Returns the variables from the config[variables] space in the order of
+    /// the declaration, except for the ones with unsatisfied `when` conditions.
+    /// Each condition is evaluated against the given context and the values
+    /// of the enabled variables declared before it.
+    pub fn get_enabled_variables(&self, context: &SerdeMap<String, SerdeValue>) -> Vec<(String, VariableOptions)> {
+        let mut current_context = context.clone();
+        let mut enabled_variables = Vec::new();
+        for (key, declaration) in self.variables.clone().unwrap_or_default() {
+            let options = match VariableOptions::from_declaration(&declaration) {
+                Ok(options) => options,
+                Err(_) => continue,
+            };
+            if !options.is_enabled(&json!(current_context)) {
+                continue
+            }
+
+            if let Some(value) = options.get_value() {
+                current_context.insert(key.to_owned(), value);
+            }
+            enabled_variables.push((key, options));
+        }
+        enabled_variables
+    }
+
+    /// Updates the value of the variable. The detailed declaration of the
+    /// variable is kept, so that its type and constraints stay available.
+    pub fn set_variable_value(&mut self, name: &str, value: SerdeValue) {
+        let mut variables = self.variables.clone().unwrap_or_default();
+        match variables.get_mut(name) {
+            Some(SerdeValue::Object(declaration)) => {
+                declaration.insert("default".to_string(), value);
+            },
+            _ => {
+                variables.insert(name.to_string(), value);
+            },
+        }
+        self.variables = Some(variables);
+    }
+
+    /// Replaces the `{{ Vars.name }}` and `{{ Storage.name }}` references in
+    /// config[storage][variables] onto the values of the variables. Storage
+    /// variables are evaluated after the storage variables they refer to.
+    pub fn replace_storage_references(&mut self, variables: &SerdeMap<String, SerdeValue>) -> Result<(), Error> {
         let mut storage_config = self.storage.clone().unwrap_or_default();
         let mut storage_variables = storage_config.variables.clone().unwrap_or_default();
 
-        for (key, serde_value) in storage_variables.clone() {
-            let updated_value = match serde_value {
-                SerdeValue::String(value) => {
-                    let raw_value = format!("{}", value);
-                    self.replace_variable_reference(&raw_value)
-                },
+        for key in get_storage_evaluation_order(&storage_variables).map_err(Error::Other)? {
+            let interpolate = |value: &String| {
+                interpolate_references(value, variables, &storage_variables)
+                    .map_err(|err| Error::Other(format!("storage.variables.{}: {}", key, err)))
+            };
+
+            let updated_value = match &storage_variables[&key] {
+                SerdeValue::String(value) => interpolate(value)?,
                 SerdeValue::Array(array) => {
-                    array
-                        .iter()
-                        .map(|value| {
-                            let raw_value = format!("{}", value);
-                            self.replace_variable_reference(&raw_value)
-                        })
-                        .collect()
+                    let mut items = Vec::new();
+                    for item in array.iter() {
+                        match item {
+                            SerdeValue::String(value) => match interpolate(value)? {
+                                // The reference to the list adds all of its items
+                                SerdeValue::Array(values) => items.extend(values),
+                                value => items.push(value),
+                            },
+                            value => items.push(value.clone()),
+                        }
+                    }
+                    SerdeValue::Array(items)
                 },
-                _ => unreachable!(),
+                value => value.clone(),
             };
 
             storage_variables.insert(key.to_owned(), updated_value);
@@ -210,6 +430,39 @@ impl JsonConfig {
 
         storage_config.variables = Some(storage_variables);
         self.storage = Some(storage_config);
+        Ok(())
+    }
+
+    /// Replaces calls of the generator functions in config[storage][variables]
+    /// onto the generated values. Each variable gets its own value.
+    pub fn generate_storage_values(&mut self, generators: &Generators) -> Result<(), Error> {
+        let mut storage_config = self.storage.clone().unwrap_or_default();
+        let mut storage_variables = storage_config.variables.clone().unwrap_or_default();
+
+        for (key, serde_value) in storage_variables.iter_mut() {
+            let name = format!("storage.variables.{}", key);
+            let generate_value = |value: &String, name: &String| {
+                generators
+                    .render_calls(value, name)
+                    .map_err(|err| Error::Other(format!("{}: {}", name, err)))
+            };
+
+            match serde_value {
+                SerdeValue::String(value) => *value = generate_value(value, &name)?,
+                SerdeValue::Array(array) => {
+                    for (index, item) in array.iter_mut().enumerate() {
+                        if let SerdeValue::String(value) = item {
+                            *value = generate_value(value, &format!("{}[{}]", name, index))?;
+                        }
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        storage_config.variables = Some(storage_variables);
+        self.storage = Some(storage_config);
+        Ok(())
     }
 
     pub fn merge_storage_with_variables(&mut self) {
@@ -221,41 +474,121 @@ impl JsonConfig {
     }
 
     pub fn validate(&self, config_path: &String) -> Result<(), Error> {
+        self.validate_meta(config_path)?;
+
         for record in self.files.sources.iter() {
-            if !record.contains_key("from") || !record.contains_key("to") {
+            if record.from.is_none() || record.to.is_none() {
                 let message = format!(
                     "{} -> Each record in sources must have specified `from` and `to` \
-                    keys. Please, check for correctness the config.json file.",
+                    keys. Please, check for correctness the config file.",
                     config_path.to_owned()
                 );
                 return Err(Error::Other(message))
             }
+
+            record.get_render_patterns()
+                .and(record.get_binary_patterns())
+                .and(record.get_include_patterns())
+                .and(record.get_exclude_patterns())
+                .map_err(|err| Error::Other(format!("{}: {}", config_path, err)))?;
         }
 
+        self.validate_delimiters(config_path)?;
+        self.validate_engine(config_path)?;
+
         let overridable_variables = self.variables.clone().unwrap_or_default();
-        self.validate_hashmap_values(config_path, "variables", &overridable_variables)?;
+        self.validate_hashmap_values(config_path, "variables", &overridable_variables.clone().into_iter().collect())?;
+        self.validate_variable_declarations(config_path, &overridable_variables)?;
 
         let storage_variables = self.storage.clone().unwrap_or_default().variables.unwrap_or_default();
         self.validate_hashmap_values(config_path, "storage.variables", &storage_variables)?;
-        self.validate_variable_references(config_path, &storage_variables)?;
+        self.validate_storage_references(config_path, &storage_variables)?;
 
         Ok(())
     }
 
-    fn replace_variable_reference(&self, value: &String) -> SerdeValue {
-        let variables = self.variables.clone().unwrap_or_default();
+    /// Checks that the template can be used with the current version of quickproj.
+    fn validate_meta(&self, config_path: &String) -> Result<(), Error> {
+        let meta = self.meta.clone().unwrap_or_default();
+        if let Some(min_version) = &meta.min_quickproj_version {
+            if !meta.is_supported_version().map_err(|err| Error::Other(format!("{}: {}", config_path, err)))? {
+                let message = format!(
+                    "{}: The template requires quickproj {} or newer, but the current version \
+                    is {}. Please, update quickproj to use this template.",
+                    config_path, min_version, QUICKPROJ_VERSION
+                );
+                return Err(Error::Other(message))
+            }
+        }
 
-        match REFERENCE_VARIABLE_REGEX.is_match(value) {
-            // It is the reference to something from the `variables`
-            true => {
-                let captures = REFERENCE_VARIABLE_REGEX.captures(value).unwrap();
-                let raw_capture = captures.name("name").unwrap();
-                let variable_name = raw_capture.as_str();
-                variables.get(variable_name).unwrap_or(&json!("")).to_owned()
-            },
-            // Regular value, specified implicitly
-            false => json!(value)
+        Ok(())
+    }
+
+    /// Checks that the template engine is supported. Custom delimiters can be
+    /// used only with the default engine.
+    fn validate_engine(&self, config_path: &String) -> Result<(), Error> {
+        let engine = self.engine.clone().unwrap_or(DEFAULT_ENGINE_NAME.to_string());
+        if !AVAILABLE_ENGINES.contains(&engine.as_str()) {
+            let message = format!(
+                "{}: The `{}` template engine isn't supported. Available engines: {}.",
+                config_path, engine, AVAILABLE_ENGINES.join(", ")
+            );
+            return Err(Error::Other(message))
+        }
+
+        let delimiters = self.delimiters.clone().unwrap_or_default();
+        let default_delimiters = DelimitersConfig::default();
+        let has_custom_delimiters =
+            delimiters.get_variable_delimiters() != default_delimiters.get_variable_delimiters() ||
+            delimiters.get_block_delimiters() != default_delimiters.get_block_delimiters() ||
+            delimiters.get_comment_delimiters() != default_delimiters.get_comment_delimiters();
+        if engine != DEFAULT_ENGINE_NAME && has_custom_delimiters {
+            let message = format!(
+                "{}: The custom delimiters are supported only by the `{}` template engine.",
+                config_path, DEFAULT_ENGINE_NAME
+            );
+            return Err(Error::Other(message))
+        }
+
+        Ok(())
+    }
+
+    fn validate_delimiters(&self, config_path: &String) -> Result<(), Error> {
+        let delimiters = self.delimiters.clone().unwrap_or_default();
+        let (variable_start, variable_end) = delimiters.get_variable_delimiters();
+        let (block_start, block_end) = delimiters.get_block_delimiters();
+        let (comment_start, comment_end) = delimiters.get_comment_delimiters();
+
+        let has_empty_delimiter = [&variable_start, &variable_end, &block_start, &block_end, &comment_start, &comment_end]
+            .iter()
+            .any(|delimiter| delimiter.trim().is_empty());
+        if has_empty_delimiter {
+            let message = format!(
+                "{}: The delimiters can't be empty or contain only whitespaces.",
+                config_path.to_owned()
+            );
+            return Err(Error::Other(message))
+        }
+
+        let start_delimiters = [
+            ("variables", &variable_start),
+            ("blocks", &block_start),
+            ("comments", &comment_start),
+        ];
+        for (index, (name, delimiter)) in start_delimiters.iter().enumerate() {
+            for (other_name, other_delimiter) in start_delimiters.iter().skip(index + 1) {
+                if delimiter.starts_with(other_delimiter.as_str()) || other_delimiter.starts_with(delimiter.as_str()) {
+                    let message = format!(
+                        "{}: The start delimiters for {} (`{}`) and {} (`{}`) \
+                        must be distinguishable from each other.",
+                        config_path.to_owned(), name, delimiter, other_name, other_delimiter
+                    );
+                    return Err(Error::Other(message))
+                }
+            }
         }
+
+        Ok(())
     }
 
     fn validate_hashmap_values(
@@ -268,10 +601,12 @@ impl JsonConfig {
             match serde_value {
                 SerdeValue::String(_) => {},
                 SerdeValue::Array(_) => {},
+                SerdeValue::Object(_) if key_prefix == "variables" => {},
                 _ => {
                     let message = format!(
                         "{}: The {}.{} key has unsupported value type. The configuration \
-                        supports only string and array of strings types.",
+                        supports only string and array of strings types, or the detailed \
+                        declaration of the variable.",
                         config_path.to_owned(), key_prefix.to_owned(), key.to_owned()
                     );
                     return Err(Error::Other(message))
@@ -282,52 +617,380 @@ impl JsonConfig {
         Ok(())
     }
 
-    fn validate_variable_references(
+    /// Checks the types, the default values and the choices of the detailed
+    /// variable declarations. The `when` conditions can refer only to the
+    /// variables declared before.
+    fn validate_variable_declarations(
         &self,
         config_path: &String,
-        hashmap: &HashMap<String, SerdeValue>,
+        variables: &SerdeMap<String, SerdeValue>,
     ) -> Result<(), Error> {
-        for (key, serde_value) in hashmap {
-            match serde_value {
-                SerdeValue::String(value) => {
-                    self.validate_reference(config_path, key, value)?
-                },
-                SerdeValue::Array(array) => {
-                    for item in array {
-                        let value = format!("{}", item);
-                        self.validate_reference(config_path, key, &value)?
-                    }
-                },
-                _ => unreachable!(),
-            };
-        };
+        let names: Vec<&String> = variables.keys().collect();
+        for (index, (key, serde_value)) in variables.iter().enumerate() {
+            let options = VariableOptions::from_declaration(serde_value).map_err(|err| {
+                let message = format!(
+                    "{}: The variables.{} key has an invalid declaration: {}.",
+                    config_path, key, err
+                );
+                Error::Other(message)
+            })?;
+
+            let condition_variables = options.get_condition()
+                .map(|condition| condition.get_variable_names())
+                .unwrap_or_default();
+            for variable_name in condition_variables {
+                let root_name = variable_name.split('.').next().unwrap_or_default().to_string();
+                let error_reason = match names.iter().position(|name| **name == root_name) {
+                    Some(position) if position == index => "refers to the variable itself",
+                    Some(position) if position > index => "refers to the variable declared after it",
+                    _ => continue,
+                };
+                let message = format!(
+                    "{}: The `when` condition of the variables.{} key {}: `{}`. \
+                    The conditions can use only the variables declared above.",
+                    config_path, key, error_reason, variable_name
+                );
+                return Err(Error::Other(message))
+            }
+        }
 
         Ok(())
     }
 
-    fn validate_reference(
+    /// Checks that the placeholders in config[storage][variables] are either
+    /// the generator calls or the references to the existing variables, and
+    /// that the storage variables don't refer to each other in a cycle.
+    fn validate_storage_references(
         &self,
         config_path: &String,
-        key: &String,
-        value: &String,
+        hashmap: &HashMap<String, SerdeValue>,
     ) -> Result<(), Error> {
-        let escaped_value = value.trim_matches('\"');
-        let is_template_variable =  escaped_value.starts_with("{{") && escaped_value.ends_with("}}");
-        let is_invalid_reference = !REFERENCE_VARIABLE_REGEX.is_match(value);
+        let variables = self.variables.clone().unwrap_or_default();
+        let mut keys: Vec<&String> = hashmap.keys().collect();
+        keys.sort();
 
-        if is_template_variable && is_invalid_reference {
-            let message = format!(
-                "{}: The storage.variables.{} key has an invalid reference. \
-                The invalid reference is `{}`. Please, make sure that the path \
-                starts with `Vars.` and the specified variable exists.",
-                config_path.to_owned(), key.to_owned(), value.to_owned()
-            );
-            return Err(Error::Other(message))
+        for key in keys {
+            let serde_value = &hashmap[key];
+            for value in get_string_values(serde_value) {
+                for placeholder in PLACEHOLDER_REGEX.find_iter(value) {
+                    let placeholder = placeholder.as_str();
+                    let is_invalid_reference = !REFERENCE_VARIABLE_REGEX.is_match(placeholder) &&
+                        !GENERATOR_PLACEHOLDER_REGEX.is_match(placeholder);
+                    if is_invalid_reference {
+                        let message = format!(
+                            "{}: The storage.variables.{} key has an invalid reference. \
+                            The invalid reference is `{}`. Please, make sure that the path \
+                            starts with `Vars.` or `Storage.` and the specified variable exists.",
+                            config_path.to_owned(), key.to_owned(), placeholder
+                        );
+                        return Err(Error::Other(message))
+                    }
+                }
+            }
+
+            for (namespace, name) in get_references(serde_value) {
+                let is_existing_variable = match namespace.as_str() {
+                    "Vars" => variables.contains_key(&name),
+                    _ => hashmap.contains_key(&name),
+                };
+                if !is_existing_variable {
+                    let message = format!(
+                        "{}: The storage.variables.{} key refers to the `{}.{}` variable, \
+                        which doesn't exist in the {} section.",
+                        config_path, key, namespace, name,
+                        match namespace.as_str() { "Vars" => "variables", _ => "storage.variables" }
+                    );
+                    return Err(Error::Other(message))
+                }
+            }
         }
+
+        get_storage_evaluation_order(hashmap)
+            .map_err(|err| Error::Other(format!("{}: {}", config_path, err)))?;
         Ok(())
     }
 }
 
+/// Returns the string values of the storage variable, including the items of the list.
+fn get_string_values(value: &SerdeValue) -> Vec<&String> {
+    match value {
+        SerdeValue::String(value) => vec![value],
+        SerdeValue::Array(array) => array.iter().filter_map(|item| match item {
+            SerdeValue::String(value) => Some(value),
+            _ => None,
+        }).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Returns the `(namespace, name)` pairs of all references in the value.
+fn get_references(value: &SerdeValue) -> Vec<(String, String)> {
+    get_string_values(value)
+        .iter()
+        .flat_map(|value| REFERENCE_VARIABLE_REGEX.captures_iter(value))
+        .map(|captures| (captures["namespace"].to_string(), captures["name"].to_string()))
+        .collect()
+}
+
+/// Returns the keys of config[storage][variables] in the order, where each
+/// variable goes after the storage variables it refers to.
+fn get_storage_evaluation_order(storage_variables: &HashMap<String, SerdeValue>) -> Result<Vec<String>, String> {
+    let mut keys: Vec<&String> = storage_variables.keys().collect();
+    keys.sort();
+
+    let mut order = Vec::new();
+    for key in keys {
+        visit_storage_variable(key, storage_variables, &mut Vec::new(), &mut order)?;
+    }
+    Ok(order)
+}
+
+fn visit_storage_variable(
+    key: &String,
+    storage_variables: &HashMap<String, SerdeValue>,
+    path: &mut Vec<String>,
+    order: &mut Vec<String>,
+) -> Result<(), String> {
+    if order.contains(key) {
+        return Ok(())
+    }
+    if let Some(position) = path.iter().position(|name| name == key) {
+        let mut cycle = path[position..].to_vec();
+        cycle.push(key.to_owned());
+        let message = format!(
+            "The storage.variables.{} key has a circular reference: {}.",
+            key, cycle.join(" -> ")
+        );
+        return Err(message)
+    }
+
+    path.push(key.to_owned());
+    if let Some(value) = storage_variables.get(key) {
+        for (namespace, name) in get_references(value) {
+            if namespace == "Storage" && storage_variables.contains_key(&name) {
+                visit_storage_variable(&name, storage_variables, path, order)?;
+            }
+        }
+    }
+    path.pop();
+
+    order.push(key.to_owned());
+    Ok(())
+}
+
+/// Replaces the references in the string onto the values of the variables.
+/// The string, that consists of the single reference, gets the value of the
+/// referenced list as is. Disabled variables are replaced onto empty strings.
+fn interpolate_references(
+    value: &str,
+    variables: &SerdeMap<String, SerdeValue>,
+    storage_variables: &HashMap<String, SerdeValue>,
+) -> Result<SerdeValue, String> {
+    let get_value = |namespace: &str, name: &str| {
+        match namespace {
+            "Vars" => Ok(variables.get(name).cloned().unwrap_or(json!(""))),
+            _ => storage_variables
+                .get(name)
+                .cloned()
+                .ok_or(format!("the `{}.{}` variable doesn't exist", namespace, name)),
+        }
+    };
+
+    if let Some(captures) = REFERENCE_VARIABLE_REGEX.captures(value) {
+        if &captures[0] == value {
+            return match get_value(&captures["namespace"], &captures["name"])? {
+                value @ SerdeValue::Array(_) => Ok(value),
+                value => Ok(json!(stringify_reference_value(&value))),
+            }
+        }
+    }
+
+    let mut output = String::new();
+    let mut position = 0;
+    for captures in REFERENCE_VARIABLE_REGEX.captures_iter(value) {
+        let captured_match = captures.get(0).unwrap();
+        output.push_str(&value[position..captured_match.start()]);
+        position = captured_match.end();
+
+        let referenced_value = get_value(&captures["namespace"], &captures["name"])?;
+        output.push_str(&stringify_reference_value(&referenced_value));
+    }
+    output.push_str(&value[position..]);
+    Ok(json!(output))
+}
+
+/// Converts the referenced value into the string. Lists are joined with commas.
+fn stringify_reference_value(value: &SerdeValue) -> String {
+    match value {
+        SerdeValue::Array(values) => values
+            .iter()
+            .filter_map(stringify_value)
+            .collect::<Vec<String>>()
+            .join(", "),
+        value => stringify_value(value).unwrap_or_default(),
+    }
+}
+
+impl MetaConfig {
+    /// Checks that the current version of quickproj isn't older than the
+    /// minimal version, required by the template.
+    pub fn is_supported_version(&self) -> Result<bool, String> {
+        let min_version = match &self.min_quickproj_version {
+            Some(min_version) => min_version,
+            None => return Ok(true),
+        };
+        let required_version = Version::parse(min_version).map_err(|err| {
+            format!(
+                "The meta.min_quickproj_version key has an invalid version `{}`: {}. \
+                Expected the version in the `MAJOR.MINOR.PATCH` format.",
+                min_version, err
+            )
+        })?;
+        let current_version = Version::parse(QUICKPROJ_VERSION).unwrap();
+        Ok(current_version >= required_version)
+    }
+
+    /// Returns the display name of the template with the version and the
+    /// author, e.g. `Rust service 1.2.0 by John Doe`.
+    pub fn get_title(&self, template_name: &String) -> String {
+        let mut title = self.name.clone().unwrap_or(template_name.to_owned());
+        if let Some(version) = &self.version {
+            title.push_str(&format!(" {}", version));
+        }
+        if let Some(author) = &self.author {
+            title.push_str(&format!(" by {}", author));
+        }
+        title
+    }
+
+    /// Returns the description, the tags and the homepage of the template,
+    /// one per line.
+    pub fn get_details(&self) -> Vec<String> {
+        let mut details = Vec::new();
+        if let Some(description) = &self.description {
+            details.push(description.to_owned());
+        }
+        if let Some(tags) = self.tags.as_ref().filter(|tags| !tags.is_empty()) {
+            details.push(format!("Tags: {}", tags.join(", ")));
+        }
+        if let Some(homepage) = &self.homepage {
+            details.push(format!("Homepage: {}", homepage));
+        }
+        if let Some(min_version) = &self.min_quickproj_version {
+            details.push(format!("Requires quickproj {} or newer.", min_version));
+        }
+        details
+    }
+}
+
+impl SourceConfig {
+    pub fn get_from(&self) -> &String {
+        self.from.as_ref().unwrap()
+    }
+
+    pub fn get_to(&self) -> &String {
+        self.to.as_ref().unwrap()
+    }
+
+    /// Builds the matcher for the paths of files, relative to the `from`
+    /// directory, that must be rendered with the template context.
+    pub fn get_render_patterns(&self) -> Result<GlobSet, Error> {
+        self.build_patterns("render", &self.render.clone().unwrap_or_default())
+    }
+
+    /// Builds the matcher for the paths of files, relative to the `from`
+    /// directory, that must be always copied as is.
+    pub fn get_binary_patterns(&self) -> Result<GlobSet, Error> {
+        self.build_patterns("binary", &self.binary.clone().unwrap_or_default())
+    }
+
+    /// Builds the matcher for the paths of files, relative to the `from`
+    /// directory, that must be copied. All files are copied when no patterns
+    /// are specified.
+    pub fn get_include_patterns(&self) -> Result<GlobSet, Error> {
+        self.build_patterns("include", &self.include.clone().unwrap_or_default())
+    }
+
+    /// Builds the matcher for the paths of files and directories, relative to
+    /// the `from` directory, that must be skipped with all their content.
+    pub fn get_exclude_patterns(&self) -> Result<GlobSet, Error> {
+        self.build_patterns("exclude", &self.exclude.clone().unwrap_or_default())
+    }
+
+    fn build_patterns(&self, key: &str, patterns: &[String]) -> Result<GlobSet, Error> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns.iter() {
+            let glob = GlobBuilder::new(pattern)
+                .literal_separator(true)
+                .build()
+                .map_err(|err| {
+                    let message = format!(
+                        "The `{}` {} pattern of the `{}` source is invalid: {}",
+                        pattern, key, self.get_from(), err
+                    );
+                    Error::Other(message)
+                })?;
+            builder.add(glob);
+        }
+
+        builder.build().map_err(|err| Error::Other(err.to_string()))
+    }
+}
+
+impl TemplateEntry {
+    pub fn get_path(&self) -> &String {
+        match self {
+            TemplateEntry::Path(path) => path,
+            TemplateEntry::Detailed(options) => &options.path,
+        }
+    }
+
+    /// Checks whether the template must be rendered with the context or
+    /// copied into the target paths as is.
+    pub fn is_rendered(&self) -> bool {
+        match self {
+            TemplateEntry::Path(_) => true,
+            TemplateEntry::Detailed(options) => options.render.unwrap_or(true),
+        }
+    }
+
+    /// Returns the explicit `binary` marker. When it isn't specified, the
+    /// binary files are detected by the content.
+    pub fn get_binary_marker(&self) -> Option<bool> {
+        match self {
+            TemplateEntry::Path(_) => None,
+            TemplateEntry::Detailed(options) => options.binary,
+        }
+    }
+}
+
+impl DelimitersConfig {
+    pub fn get_variable_delimiters(&self) -> (String, String) {
+        self.variable.clone()
+            .unwrap_or_else(|| DelimitersConfig::default().variable.unwrap())
+    }
+
+    pub fn get_block_delimiters(&self) -> (String, String) {
+        self.block.clone()
+            .unwrap_or_else(|| DelimitersConfig::default().block.unwrap())
+    }
+
+    pub fn get_comment_delimiters(&self) -> (String, String) {
+        self.comment.clone()
+            .unwrap_or_else(|| DelimitersConfig::default().comment.unwrap())
+    }
+}
+
+impl Default for DelimitersConfig {
+    fn default() -> Self {
+        DelimitersConfig {
+            variable: Some((String::from("{{"), String::from("}}"))),
+            block: Some((String::from("{%"), String::from("%}"))),
+            comment: Some((String::from("{#"), String::from("#}"))),
+        }
+    }
+}
+
 impl Default for ScriptsConfig {
     fn default() -> Self {
         ScriptsConfig {
@@ -344,22 +1007,378 @@ impl Default for StorageConfig {
     }
 }
 
+/// Reads the config in the JSON, YAML or TOML format, depending on the
+/// extension of the file, and merges it into the base config from the
+/// `extends` key, relative to the config. The chain contains the configs
+/// which are being read, in order to detect circular inheritance.
+fn read_config_value(path: &PathBuf, chain: &mut Vec<PathBuf>) -> Result<SerdeValue, Error> {
+    let mut data = String::new();
+    File::open(path)
+        .and_then(|mut f| f.read_to_string(&mut data))
+        .context(path)?;
+
+    let parsed_value: Result<SerdeValue, String> = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("yaml") => serde_yaml::from_str(&data).map_err(|err| err.to_string()),
+        Some("toml") => toml::from_str(&data).map_err(|err| err.to_string()),
+        _ => serde_json::from_str(&data).map_err(|err| err.to_string()),
+    };
+    let mut value = parsed_value.map_err(|err| Error::Other(format!("{}: {}", path.display(), err)))?;
+
+    let base_path = match value.as_object_mut().and_then(|map| map.remove("extends")) {
+        Some(SerdeValue::String(base_path)) => base_path,
+        Some(_) => {
+            let message = format!("{}: The `extends` key must be the path to the base config.", path.display());
+            return Err(Error::Other(message))
+        },
+        None => return Ok(value),
+    };
+
+    let canonical_path = path.canonicalize().context(path)?;
+    chain.push(canonical_path);
+    let base_path = path.parent().unwrap_or(Path::new(".")).join(base_path);
+    if let Some(position) = base_path.canonicalize().ok().and_then(|base| chain.iter().position(|item| *item == base)) {
+        let cycle = chain[position..]
+            .iter()
+            .chain(chain.get(position))
+            .map(|item| item.display().to_string())
+            .collect::<Vec<String>>()
+            .join(" -> ");
+        let message = format!("{}: The configuration has circular inheritance: {}.", path.display(), cycle);
+        return Err(Error::Other(message))
+    }
+
+    let mut base_value = read_config_value(&base_path, chain)?;
+    chain.pop();
+    merge_config_values(&mut base_value, value);
+    Ok(base_value)
+}
+
+/// Deep merges the child config into the base config. Objects are merged
+/// key by key, other values (including arrays) of the child replace the
+/// values of the base config.
+fn merge_config_values(base: &mut SerdeValue, child: SerdeValue) {
+    match (base, child) {
+        (SerdeValue::Object(base_map), SerdeValue::Object(child_map)) => {
+            for (key, child_value) in child_map {
+                match base_map.get_mut(&key) {
+                    Some(base_value) => merge_config_values(base_value, child_value),
+                    None => {
+                        base_map.insert(key, child_value);
+                    },
+                }
+            }
+        },
+        (base, child) => *base = child,
+    }
+}
+
+/// Loads configs of the templates and of all templates they require. The
+/// configs are ordered so that each template goes after its dependencies.
 pub fn get_template_configs(
-    project_name: &String,
+    target_directory: &String,
     templates: &Vec<String>,
     defined_templates: &HashMap<String, String>,
-) -> Result<HashMap<String, Box<Config>>, Error> {
-    let mut configs = HashMap::new();
+) -> Result<IndexMap<String, Box<Config>>, Error> {
+    let project_name = basename(target_directory, '/');
+    let mut json_configs = IndexMap::new();
 
     for template_name in templates {
-        let dir = defined_templates.get(template_name).unwrap();
-        let path = format!("{}/{}", dir, CONFIG_NAME);
-        let json_config = JsonConfig::from_file(&path)?;
-        let config = Config::new(json_config)
-            .with_project_name(&project_name.to_owned())
-            .with_template_name(&template_name.to_owned());
-        configs.insert(template_name.clone(), Box::new(config));
+        load_template_config(template_name, defined_templates, &mut Vec::new(), &mut json_configs)?;
     }
 
+    let configs = json_configs
+        .into_iter()
+        .map(|(template_name, json_config)| {
+            let config = Config::new(json_config)
+                .with_project_name(&project_name.to_owned())
+                .with_template_name(&template_name)
+                .with_target_directory(&PathBuf::from(target_directory));
+            (template_name, Box::new(config))
+        })
+        .collect();
     Ok(configs)
 }
+
+/// Loads the config of the template after the configs of its dependencies.
+/// The path contains the templates, which are being loaded, in order to
+/// detect circular dependencies.
+fn load_template_config(
+    template_name: &String,
+    defined_templates: &HashMap<String, String>,
+    path: &mut Vec<String>,
+    configs: &mut IndexMap<String, JsonConfig>,
+) -> Result<(), Error> {
+    if configs.contains_key(template_name) {
+        return Ok(())
+    }
+    if let Some(position) = path.iter().position(|name| name == template_name) {
+        let mut cycle = path[position..].to_vec();
+        cycle.push(template_name.to_owned());
+        let message = format!(
+            "The templates have circular dependencies: {}. Please, check the `requires` \
+            key in the configuration files.",
+            cycle.join(" -> ")
+        );
+        return Err(Error::Other(message))
+    }
+
+    let dir = defined_templates.get(template_name).unwrap();
+    let config_path = get_config_path(&PathBuf::from(dir))?.display().to_string();
+    let json_config = JsonConfig::from_file(&config_path)?;
+
+    path.push(template_name.to_owned());
+    for dependency in json_config.requires.clone().unwrap_or_default() {
+        if !defined_templates.contains_key(&dependency) {
+            let message = format!(
+                "{}: The `{}` template requires the `{}` template, which isn't installed. \
+                Please, install it first.",
+                config_path, template_name, dependency
+            );
+            return Err(Error::Other(message))
+        }
+        load_template_config(&dependency, defined_templates, path, configs)?;
+    }
+    path.pop();
+
+    configs.insert(template_name.to_owned(), json_config);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn get_config(variables: SerdeValue) -> JsonConfig {
+        serde_json::from_value(json!({"files": {"sources": []}, "variables": variables})).unwrap()
+    }
+
+    fn get_storage_config(variables: SerdeValue, storage_variables: SerdeValue) -> JsonConfig {
+        let value = json!({
+            "files": {"sources": []},
+            "variables": variables,
+            "storage": {"variables": storage_variables},
+        });
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn get_storage_value(config: &JsonConfig, name: &str) -> SerdeValue {
+        config.storage.clone().unwrap().variables.unwrap()[name].clone()
+    }
+
+    /// Writes the configs of the templates into the directory and returns the
+    /// paths of the templates by their names.
+    fn write_templates(directory: &Path, configs: SerdeValue) -> HashMap<String, String> {
+        let mut templates = HashMap::new();
+        for (name, config) in configs.as_object().unwrap() {
+            let template_directory = directory.join(name);
+            fs::create_dir_all(&template_directory).unwrap();
+            fs::write(template_directory.join("config.json"), config.to_string()).unwrap();
+            templates.insert(name.to_owned(), template_directory.display().to_string());
+        }
+        templates
+    }
+
+    fn get_template_names(templates: &[&str], defined_templates: &HashMap<String, String>) -> Result<Vec<String>, String> {
+        let templates = templates.iter().map(|name| name.to_string()).collect();
+        get_template_configs(&String::from("app"), &templates, defined_templates)
+            .map(|configs| configs.into_keys().collect())
+            .map_err(|err| err.to_string())
+    }
+
+    fn get_validation_error(config: &JsonConfig) -> String {
+        match config.validate(&String::from("config.json")) {
+            Err(err) => err.to_string(),
+            Ok(_) => panic!("expected the invalid config"),
+        }
+    }
+
+    #[test]
+    fn test_enabled_variables_depend_on_previous_values() {
+        let mut config = get_config(json!({
+            "ci": {"type": "bool", "default": false},
+            "ci_provider": {"choices": ["github", "gitlab"], "when": "ci"},
+            "docker": {"type": "bool", "default": true},
+            "registry": {"default": "ghcr.io", "when": "docker and ci_provider == 'github'"},
+        }));
+        let get_names = |config: &JsonConfig| -> Vec<String> {
+            config.get_enabled_variables(&SerdeMap::new()).into_iter().map(|(name, _)| name).collect()
+        };
+
+        assert_eq!(get_names(&config), vec!["ci", "docker"]);
+        config.set_variable_value("ci", json!(true));
+        assert_eq!(get_names(&config), vec!["ci", "ci_provider", "docker", "registry"]);
+        config.set_variable_value("ci_provider", json!("gitlab"));
+        assert_eq!(get_names(&config), vec!["ci", "ci_provider", "docker"]);
+    }
+
+    #[test]
+    fn test_conditions_refer_to_previous_variables() {
+        let config = get_config(json!({
+            "registry": {"default": "ghcr.io", "when": "docker"},
+            "docker": {"type": "bool", "default": true},
+        }));
+        assert_eq!(
+            get_validation_error(&config),
+            "config.json: The `when` condition of the variables.registry key refers to the variable \
+            declared after it: `docker`. The conditions can use only the variables declared above."
+        );
+
+        let config = get_config(json!({"docker": {"type": "bool", "when": "not docker.enabled"}}));
+        assert!(get_validation_error(&config).contains("refers to the variable itself: `docker.enabled`"));
+
+        let config = get_config(json!({"docker": {"type": "bool", "when": "docker ="}}));
+        assert!(get_validation_error(&config).starts_with(
+            "config.json: The variables.docker key has an invalid declaration: invalid `when` condition `docker =`"
+        ));
+    }
+
+    #[test]
+    fn test_storage_references_are_resolved_in_order() {
+        let mut config = get_storage_config(json!({"name": "app", "envs": ["dev", "prod"]}), json!({
+            "url": "https://{{ Storage.host }}/{{ Vars.name }}",
+            "host": "{{ Storage.domain }}:8080",
+            "domain": "{{ Vars.name }}.example.com",
+            "all_envs": ["local", "{{ Vars.envs }}"],
+            "summary": "{{ Vars.name }}: {{ Vars.envs }}",
+            "port": 8080,
+        }));
+        let variables = config.variables.clone().unwrap();
+        config.replace_storage_references(&variables).unwrap();
+
+        assert_eq!(get_storage_value(&config, "url"), json!("https://app.example.com:8080/app"));
+        // The reference to the list adds all of its items
+        assert_eq!(get_storage_value(&config, "all_envs"), json!(["local", "dev", "prod"]));
+        assert_eq!(get_storage_value(&config, "summary"), json!("app: dev, prod"));
+        assert_eq!(get_storage_value(&config, "port"), json!(8080));
+    }
+
+    #[test]
+    fn test_circular_storage_references_are_errors() {
+        let config = get_storage_config(json!({}), json!({
+            "a": "{{ Storage.b }}",
+            "b": "{{ Storage.c }}",
+            "c": "{{ Storage.a }}",
+        }));
+        assert_eq!(
+            get_validation_error(&config),
+            "config.json: The storage.variables.a key has a circular reference: a -> b -> c -> a."
+        );
+
+        let config = get_storage_config(json!({}), json!({"a": ["x", "{{ Storage.a }}"]}));
+        assert_eq!(
+            get_validation_error(&config),
+            "config.json: The storage.variables.a key has a circular reference: a -> a."
+        );
+    }
+
+    #[test]
+    fn test_missing_storage_references_are_errors() {
+        let config = get_storage_config(json!({"name": "app"}), json!({"url": "{{ Vars.host }}"}));
+        assert_eq!(
+            get_validation_error(&config),
+            "config.json: The storage.variables.url key refers to the `Vars.host` variable, \
+            which doesn't exist in the variables section."
+        );
+
+        let config = get_storage_config(json!({}), json!({"url": "{{ Storage.host }}"}));
+        assert!(get_validation_error(&config).contains("doesn't exist in the storage.variables section"));
+
+        let config = get_storage_config(json!({}), json!({"url": "{{ host }}"}));
+        assert!(get_validation_error(&config).contains("The invalid reference is `{{ host }}`"));
+
+        let result = interpolate_references("{{ Storage.host }}", &SerdeMap::new(), &HashMap::new());
+        assert_eq!(result, Err(String::from("the `Storage.host` variable doesn't exist")));
+    }
+
+    #[test]
+    fn test_required_templates_go_first() {
+        let directory = tempfile::tempdir().unwrap();
+        let templates = write_templates(directory.path(), json!({
+            "base": {"files": {"sources": []}},
+            "ci": {"requires": ["base"], "files": {"sources": []}},
+            "docker": {"requires": ["base"], "files": {"sources": []}},
+            "service": {"requires": ["ci", "docker"], "files": {"sources": []}},
+        }));
+
+        assert_eq!(get_template_names(&["service"], &templates), Ok(vec![
+            String::from("base"), String::from("ci"), String::from("docker"), String::from("service"),
+        ]));
+        // Each template is used once, even if it's also passed explicitly
+        assert_eq!(get_template_names(&["docker", "service", "base"], &templates), Ok(vec![
+            String::from("base"), String::from("docker"), String::from("ci"), String::from("service"),
+        ]));
+    }
+
+    #[test]
+    fn test_invalid_required_templates_are_errors() {
+        let directory = tempfile::tempdir().unwrap();
+        let templates = write_templates(directory.path(), json!({
+            "a": {"requires": ["b"], "files": {"sources": []}},
+            "b": {"requires": ["c"], "files": {"sources": []}},
+            "c": {"requires": ["a"], "files": {"sources": []}},
+            "d": {"requires": ["missing"], "files": {"sources": []}},
+        }));
+
+        assert_eq!(
+            get_template_names(&["a"], &templates),
+            Err(String::from(
+                "The templates have circular dependencies: a -> b -> c -> a. Please, check \
+                the `requires` key in the configuration files."
+            ))
+        );
+        let message = get_template_names(&["d"], &templates).unwrap_err();
+        assert!(message.ends_with(
+            "config.json: The `d` template requires the `missing` template, which isn't installed. \
+            Please, install it first."
+        ));
+    }
+
+    #[test]
+    fn test_configs_are_merged_into_base_configs() {
+        let directory = tempfile::tempdir().unwrap();
+        fs::create_dir_all(directory.path().join("_base")).unwrap();
+        fs::create_dir_all(directory.path().join("api")).unwrap();
+        fs::write(directory.path().join("_base/root.toml"), "[meta]\nauthor = 'Platform team'\n").unwrap();
+        fs::write(
+            directory.path().join("_base/base.yaml"),
+            "extends: root.toml\nvariables:\n  org: acme\n  service: base\nscripts:\n  after_init: [make, test]\n",
+        ).unwrap();
+        let config_path = directory.path().join("api/config.json");
+        let config = json!({
+            "extends": "../_base/base.yaml",
+            "meta": {"name": "API"},
+            "variables": {"service": "api"},
+            "scripts": {"after_init": ["make"]},
+        });
+        fs::write(&config_path, config.to_string()).unwrap();
+
+        let value = read_config_value(&config_path, &mut Vec::new()).unwrap();
+        assert_eq!(value, json!({
+            "meta": {"author": "Platform team", "name": "API"},
+            "variables": {"org": "acme", "service": "api"},
+            // Arrays of the child config replace the arrays of the base config
+            "scripts": {"after_init": ["make"]},
+        }));
+    }
+
+    #[test]
+    fn test_circular_inheritance_is_error() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = |name: &str| directory.path().join(name);
+        fs::write(path("a.json"), json!({"extends": "b.yaml"}).to_string()).unwrap();
+        fs::write(path("b.yaml"), "extends: a.json\n").unwrap();
+        fs::write(path("c.json"), json!({"extends": 1}).to_string()).unwrap();
+
+        let message = read_config_value(&path("a.json"), &mut Vec::new()).unwrap_err().to_string();
+        let canonical_path = |name: &str| path(name).canonicalize().unwrap().display().to_string();
+        assert_eq!(message, format!(
+            "{}: The configuration has circular inheritance: {} -> {} -> {}.",
+            path("b.yaml").display(), canonical_path("a.json"), canonical_path("b.yaml"), canonical_path("a.json")
+        ));
+
+        let message = read_config_value(&path("c.json"), &mut Vec::new()).unwrap_err().to_string();
+        assert!(message.ends_with("The `extends` key must be the path to the base config."));
+    }
+}
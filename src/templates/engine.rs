@@ -0,0 +1,43 @@
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use quick_error::ResultExt;
+use serde_json::Value as SerdeValue;
+
+use crate::error::Error;
+use crate::templates::renderer::RenderedTemplate;
+
+pub static DEFAULT_ENGINE_NAME: &str = "default";
+pub static TERA_ENGINE_NAME: &str = "tera";
+pub static AVAILABLE_ENGINES: [&str; 2] = [DEFAULT_ENGINE_NAME, TERA_ENGINE_NAME];
+
+/// Renders template files and paths of the generated files with the context.
+pub trait TemplateEngine {
+    /// Extract all variables used in the string, e.g. in the path of the generated file.
+    fn get_template_variables(&self, data: &str) -> BTreeSet<String>;
+
+    /// Extract all variables used in the template file.
+    fn get_file_variables(&self, template_path: &Path) -> Result<BTreeSet<String>, Error>;
+
+    /// Renders the template file with the context. Errors are prefixed with
+    /// the path to the template.
+    fn render_file(&self, template_path: &Path, context: &SerdeValue) -> Result<RenderedTemplate, Error>;
+
+    /// Renders string from the given data and the context.
+    fn render_template(&self, data: &str, context: &SerdeValue) -> Result<String, Error>;
+
+    /// Generates new file based on the template with specified context.
+    fn generate_file_from_template(
+        &self,
+        context: &SerdeValue,
+        template_path: &Path,
+        out_file_path: &Path,
+    ) -> Result<(), Error> {
+        let rendered_template = self.render_file(template_path, context)?;
+        let mut file = File::create(out_file_path).context(out_file_path)?;
+        file.write_all(rendered_template.data.as_bytes()).context(out_file_path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,314 @@
+use heck::{CamelCase, KebabCase, MixedCase, ShoutySnakeCase, SnakeCase, TitleCase};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde_json::{json, Value as SerdeValue};
+
+use crate::error::Error;
+use crate::templates::generators::FunctionCall;
+
+lazy_static! {
+    static ref FILTER_REGEX: Regex = Regex::new(r"^(?P<name>\w+)\s*(?:\((?P<args>.*)\))?$").unwrap();
+    static ref VARIABLE_NAME_REGEX: Regex = Regex::new(r"^[\w.:-]+$").unwrap();
+}
+
+/// Parsed content of the `{{ variable | filter | filter(arg) }}` placeholder.
+/// Instead of the variable can be used the call of the generator function.
+#[derive(Debug, Clone)]
+pub struct Placeholder {
+    pub variable_name: String,
+    pub function: Option<FunctionCall>,
+    pub filters: Vec<Filter>,
+}
+
+/// Filter applied to the value of the placeholder.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    pub name: String,
+    pub args: Vec<String>,
+}
+
+impl Placeholder {
+    /// Parses the expression of the placeholder (without the surrounding braces).
+    pub fn parse(expression: &str) -> Result<Placeholder, Error> {
+        let mut parts = split_outside_quotes(expression, '|').into_iter();
+        let variable_name = parts.next().unwrap_or_default().trim().to_string();
+        let function = match FunctionCall::parse(&variable_name) {
+            Some(function_call) => Some(function_call?),
+            None => None,
+        };
+        if function.is_none() && !VARIABLE_NAME_REGEX.is_match(&variable_name) {
+            let message = format!("invalid variable name `{}`", variable_name);
+            return Err(Error::Other(message))
+        }
+
+        let mut filters = Vec::new();
+        for part in parts {
+            filters.push(Filter::parse(part.trim())?);
+        }
+
+        Ok(Placeholder { variable_name, function, filters })
+    }
+
+    /// Applies the filters in the declaration order to the given value.
+    /// The missing value is represented as `None` and is passed as is through
+    /// the all filters, except the `default` filter.
+    pub fn apply_filters(&self, value: Option<SerdeValue>) -> Result<Option<SerdeValue>, Error> {
+        self.filters
+            .iter()
+            .try_fold(value, |value, filter| filter.apply(value))
+    }
+}
+
+impl Filter {
+    fn parse(data: &str) -> Result<Filter, Error> {
+        let captures = match FILTER_REGEX.captures(data) {
+            Some(captures) => captures,
+            None => {
+                let message = format!("invalid filter `{}`", data);
+                return Err(Error::Other(message))
+            }
+        };
+
+        let name = captures["name"].to_string();
+        let args = match captures.name("args") {
+            Some(raw_args) if !raw_args.as_str().trim().is_empty() => {
+                split_outside_quotes(raw_args.as_str(), ',')
+                    .iter()
+                    .map(|arg| unquote(arg.trim()))
+                    .collect()
+            },
+            _ => Vec::new(),
+        };
+
+        let filter = Filter { name, args };
+        filter.validate()?;
+        Ok(filter)
+    }
+
+    /// Checks that the filter exists and has got the expected amount of arguments.
+    fn validate(&self) -> Result<(), Error> {
+        let (min_args, max_args) = match self.name.as_str() {
+            "upper" | "lower" | "capitalize" | "title" | "trim" |
+            "snake_case" | "kebab_case" | "pascal_case" | "camel_case" |
+            "shouty_snake_case" | "length" | "first" | "last" => (0, 0),
+            "join" => (0, 1),
+            "default" => (1, 1),
+            "replace" => (2, 2),
+            _ => {
+                let message = format!("unknown `{}` filter", self.name);
+                return Err(Error::Other(message))
+            }
+        };
+
+        if self.args.len() < min_args || self.args.len() > max_args {
+            let message = format!(
+                "the `{}` filter expects {} argument(s), but got {}",
+                self.name,
+                match min_args == max_args {
+                    true => min_args.to_string(),
+                    false => format!("from {} to {}", min_args, max_args),
+                },
+                self.args.len()
+            );
+            return Err(Error::Other(message))
+        }
+
+        Ok(())
+    }
+
+    fn apply(&self, value: Option<SerdeValue>) -> Result<Option<SerdeValue>, Error> {
+        if self.name == "default" {
+            let is_empty = match &value {
+                None | Some(SerdeValue::Null) => true,
+                Some(SerdeValue::String(data)) => data.is_empty(),
+                Some(SerdeValue::Array(values)) => values.is_empty(),
+                _ => false,
+            };
+
+            return match is_empty {
+                true => Ok(Some(json!(self.args[0]))),
+                false => Ok(value),
+            }
+        }
+
+        let value = match value {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+
+        let result = match self.name.as_str() {
+            "upper" => map_strings(value, |data| data.to_uppercase()),
+            "lower" => map_strings(value, |data| data.to_lowercase()),
+            "capitalize" => map_strings(value, capitalize),
+            "title" => map_strings(value, |data| data.to_title_case()),
+            "trim" => map_strings(value, |data| data.trim().to_string()),
+            "snake_case" => map_strings(value, |data| data.to_snake_case()),
+            "kebab_case" => map_strings(value, |data| data.to_kebab_case()),
+            "pascal_case" => map_strings(value, |data| data.to_camel_case()),
+            "camel_case" => map_strings(value, |data| data.to_mixed_case()),
+            "shouty_snake_case" => map_strings(value, |data| data.to_shouty_snake_case()),
+            "replace" => {
+                let (from, to) = (self.args[0].clone(), self.args[1].clone());
+                map_strings(value, move |data| data.replace(&from, &to))
+            },
+            "join" => {
+                let separator = self.args.first().cloned().unwrap_or_default();
+                match value {
+                    SerdeValue::Array(values) => {
+                        let items: Vec<String> = values
+                            .iter()
+                            .map(|item| match item {
+                                SerdeValue::String(data) => data.to_owned(),
+                                _ => item.to_string(),
+                            })
+                            .collect();
+                        json!(items.join(&separator))
+                    },
+                    _ => value,
+                }
+            },
+            "length" => match value {
+                SerdeValue::String(data) => json!(data.chars().count()),
+                SerdeValue::Array(values) => json!(values.len()),
+                SerdeValue::Object(map) => json!(map.len()),
+                _ => value,
+            },
+            "first" => match value {
+                SerdeValue::Array(values) => values.first().cloned().unwrap_or(SerdeValue::Null),
+                _ => value,
+            },
+            "last" => match value {
+                SerdeValue::Array(values) => values.last().cloned().unwrap_or(SerdeValue::Null),
+                _ => value,
+            },
+            _ => unreachable!(),
+        };
+
+        Ok(Some(result))
+    }
+}
+
+/// Applies the function to the string value or to each string of the array.
+fn map_strings<F>(value: SerdeValue, function: F) -> SerdeValue
+    where F: Fn(&str) -> String
+{
+    match value {
+        SerdeValue::String(data) => json!(function(&data)),
+        SerdeValue::Array(values) => {
+            let items: Vec<SerdeValue> = values
+                .into_iter()
+                .map(|item| match item {
+                    SerdeValue::String(data) => json!(function(&data)),
+                    _ => item,
+                })
+                .collect();
+            json!(items)
+        },
+        _ => value,
+    }
+}
+
+fn capitalize(data: &str) -> String {
+    let mut chars = data.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars.flat_map(|c| c.to_lowercase())).collect(),
+        None => String::new(),
+    }
+}
+
+/// Splits the string by the separator, ignoring separators inside of the
+/// single or double quoted strings.
+pub fn split_outside_quotes(data: &str, separator: char) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+
+    for symbol in data.chars() {
+        match quote {
+            Some(opened_quote) if symbol == opened_quote => quote = None,
+            None if symbol == '"' || symbol == '\'' => quote = Some(symbol),
+            None if symbol == separator => {
+                parts.push(current.clone());
+                current.clear();
+                continue
+            },
+            _ => {},
+        }
+        current.push(symbol);
+    }
+
+    parts.push(current);
+    parts
+}
+
+/// Removes the surrounding quotes from the filter argument.
+fn unquote(data: &str) -> String {
+    let is_quoted = data.len() >= 2 && (
+        (data.starts_with('"') && data.ends_with('"')) ||
+        (data.starts_with('\'') && data.ends_with('\''))
+    );
+
+    match is_quoted {
+        true => data[1..data.len() - 1].to_string(),
+        false => data.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn render(expression: &str, value: Option<SerdeValue>) -> Option<SerdeValue> {
+        Placeholder::parse(expression).unwrap().apply_filters(value).unwrap()
+    }
+
+    #[test]
+    fn test_filter_arguments() {
+        let placeholder = Placeholder::parse("name | replace(\"a, b\", 'c|d') | join(', ') | upper").unwrap();
+        assert_eq!(placeholder.variable_name, "name");
+        let filters: Vec<(&str, Vec<String>)> = placeholder.filters
+            .iter()
+            .map(|filter| (filter.name.as_str(), filter.args.clone()))
+            .collect();
+        assert_eq!(filters, vec![
+            ("replace", vec!["a, b".to_string(), "c|d".to_string()]),
+            ("join", vec![", ".to_string()]),
+            ("upper", Vec::new()),
+        ]);
+    }
+
+    #[test]
+    fn test_invalid_filters() {
+        let message = |expression: &str| Placeholder::parse(expression).unwrap_err().to_string();
+        assert_eq!(message("name | unknown"), "unknown `unknown` filter");
+        assert_eq!(message("name | replace('a')"), "the `replace` filter expects 2 argument(s), but got 1");
+        assert_eq!(message("name | join(1, 2)"), "the `join` filter expects from 0 to 1 argument(s), but got 2");
+        assert_eq!(message("name | upper("), "invalid filter `upper(`");
+        assert_eq!(message("first name"), "invalid variable name `first name`");
+    }
+
+    #[test]
+    fn test_filters_are_applied_in_order() {
+        assert_eq!(render("name | replace('-', ' ') | title", Some(json!("my-app"))), Some(json!("My App")));
+        assert_eq!(render("name | pascal_case | lower", Some(json!("my app"))), Some(json!("myapp")));
+        assert_eq!(render("items | upper | join('+')", Some(json!(["a", "b"]))), Some(json!("A+B")));
+        assert_eq!(render("items | first", Some(json!([1, 2]))), Some(json!(1)));
+        assert_eq!(render("items | length", Some(json!(["a", "b"]))), Some(json!(2)));
+    }
+
+    #[test]
+    fn test_default_filter() {
+        assert_eq!(render("name | default('app') | upper", None), Some(json!("APP")));
+        assert_eq!(render("name | default('app')", Some(json!(""))), Some(json!("app")));
+        assert_eq!(render("name | default('app')", Some(json!("web"))), Some(json!("web")));
+        assert_eq!(render("name | upper", None), None);
+    }
+
+    #[test]
+    fn test_split_outside_quotes() {
+        assert_eq!(split_outside_quotes("a | 'b | c' | \"d|e\"", '|'), vec!["a ", " 'b | c' ", " \"d|e\""]);
+        assert_eq!(split_outside_quotes("", ','), vec![""]);
+    }
+}
@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+use std::net::TcpListener;
+use std::sync::Mutex;
+
+use lazy_static::lazy_static;
+use rand::distributions::Alphanumeric;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use regex::Regex;
+use uuid::Builder as UuidBuilder;
+
+use crate::error::Error;
+use crate::templates::filters::split_outside_quotes;
+
+lazy_static! {
+    static ref FUNCTION_CALL_REGEX: Regex = Regex::new(r"^(?P<name>\w+)\s*\((?P<args>.*)\)$").unwrap();
+    pub static ref GENERATOR_PLACEHOLDER_REGEX: Regex = Regex::new(r"\{\{\s*(?P<call>\w+\s*\([^)]*\))\s*}}").unwrap();
+}
+
+static GENERATOR_NAMES: [&str; 3] = ["uuid", "random_string", "random_port"];
+static MAX_RANDOM_STRING_LENGTH: usize = 4096;
+static MAX_PORT_LOOKUP_ATTEMPTS: usize = 100;
+
+/// Call of the generator function, e.g. `uuid()` or `random_string(32)`.
+#[derive(Debug, Clone)]
+pub struct FunctionCall {
+    pub name: String,
+    pub args: Vec<String>,
+}
+
+/// Generates unique identifiers, random strings and free ports. The same
+/// function calls return the same values during the project initialization,
+/// so that all generated files stay consistent with each other.
+#[derive(Debug)]
+pub struct Generators {
+    seed: Option<u64>,
+    values: Mutex<HashMap<String, String>>,
+}
+
+impl FunctionCall {
+    /// Parses the function call. Returns `None` for expressions that aren't
+    /// the calls of the generator functions, e.g. `hashFiles('**/Cargo.lock')`
+    /// in GitHub Actions workflows.
+    pub fn parse(expression: &str) -> Option<Result<FunctionCall, Error>> {
+        let captures = FUNCTION_CALL_REGEX.captures(expression.trim())?;
+        if !GENERATOR_NAMES.contains(&&captures["name"]) {
+            return None
+        }
+
+        let args = match captures["args"].trim().is_empty() {
+            true => Vec::new(),
+            false => {
+                split_outside_quotes(&captures["args"], ',')
+                    .iter()
+                    .map(|arg| arg.trim().to_string())
+                    .collect()
+            }
+        };
+
+        Some(FunctionCall::new(&captures["name"], args))
+    }
+
+    /// Creates the function call with the arguments, represented as strings.
+    pub fn new(name: &str, args: Vec<String>) -> Result<FunctionCall, Error> {
+        let function_call = FunctionCall { name: name.to_string(), args };
+        function_call.validate().map(|_| function_call)
+    }
+
+    /// Checks that the function exists and has got the expected arguments.
+    fn validate(&self) -> Result<(), Error> {
+        let expected_args: &[usize] = match self.name.as_str() {
+            "uuid" => &[0],
+            "random_string" => &[1],
+            "random_port" => &[0, 2],
+            _ => {
+                let message = format!("unknown `{}` function", self.name);
+                return Err(Error::Other(message))
+            }
+        };
+
+        if !expected_args.contains(&self.args.len()) {
+            let message = format!(
+                "the `{}` function expects {} argument(s), but got {}",
+                self.name,
+                expected_args.iter().map(|count| count.to_string()).collect::<Vec<String>>().join(" or "),
+                self.args.len()
+            );
+            return Err(Error::Other(message))
+        }
+
+        let numbers = self.get_numeric_args()?;
+        match self.name.as_str() {
+            "random_string" if numbers[0] == 0 || numbers[0] > MAX_RANDOM_STRING_LENGTH => {
+                let message = format!(
+                    "the length of the random string must be in the range from 1 to {}",
+                    MAX_RANDOM_STRING_LENGTH
+                );
+                Err(Error::Other(message))
+            },
+            "random_port" if !numbers.is_empty() && (numbers[0] > numbers[1] || numbers[1] > 65535) => {
+                let message = format!(
+                    "invalid range of ports from {} to {}",
+                    numbers[0], numbers[1]
+                );
+                Err(Error::Other(message))
+            },
+            _ => Ok(()),
+        }
+    }
+
+    fn get_numeric_args(&self) -> Result<Vec<usize>, Error> {
+        self.args
+            .iter()
+            .map(|arg| {
+                arg.parse::<usize>().map_err(|_| {
+                    let message = format!("the `{}` function expects numeric arguments, but got `{}`", self.name, arg);
+                    Error::Other(message)
+                })
+            })
+            .collect()
+    }
+
+    fn get_key(&self) -> String {
+        format!("{}({})", self.name, self.args.join(", "))
+    }
+}
+
+impl Generators {
+    /// Creates generators, which return the same values for the same seed.
+    pub fn new(seed: Option<u64>) -> Self {
+        Generators {
+            seed,
+            values: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the value of the function call. The value is generated only once
+    /// and reused for the same calls later.
+    pub fn get_value(&self, function_call: &FunctionCall) -> Result<String, Error> {
+        let key = function_call.get_key();
+        let mut values = self.values.lock().unwrap();
+        if let Some(value) = values.get(&key) {
+            return Ok(value.to_owned())
+        }
+
+        let value = self.generate(function_call, &key)?;
+        values.insert(key, value.clone());
+        Ok(value)
+    }
+
+    /// Generates a new value of the function call. With the specified seed the
+    /// value depends only on the seed and the given key, so that the output
+    /// doesn't depend on the order of the calls.
+    fn generate(&self, function_call: &FunctionCall, key: &str) -> Result<String, Error> {
+        let numbers = function_call.get_numeric_args()?;
+        let mut rng = match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed ^ get_stable_hash(key)),
+            None => StdRng::from_entropy(),
+        };
+        match function_call.name.as_str() {
+            "uuid" => {
+                let bytes: [u8; 16] = rng.gen();
+                Ok(UuidBuilder::from_random_bytes(bytes).into_uuid().to_string())
+            },
+            "random_string" => {
+                let value = (&mut rng)
+                    .sample_iter(&Alphanumeric)
+                    .take(numbers[0])
+                    .map(char::from)
+                    .collect();
+                Ok(value)
+            },
+            "random_port" => {
+                let (min_port, max_port) = match numbers.is_empty() {
+                    true => (1024, 65535),
+                    false => (numbers[0], numbers[1]),
+                };
+
+                (0..MAX_PORT_LOOKUP_ATTEMPTS)
+                    .map(|_| rng.gen_range(min_port..=max_port) as u16)
+                    .find(|port| TcpListener::bind(("127.0.0.1", *port)).is_ok())
+                    .map(|port| port.to_string())
+                    .ok_or_else(|| {
+                        let message = format!("a free port in the range from {} to {} wasn't found", min_port, max_port);
+                        Error::Other(message)
+                    })
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    /// Replaces all `{{ function() }}` placeholders in the string onto newly
+    /// generated values. The name distinguishes values of the same calls.
+    pub fn render_calls(&self, data: &str, name: &str) -> Result<String, Error> {
+        let mut output = String::new();
+        let mut position = 0;
+        for (index, captures) in GENERATOR_PLACEHOLDER_REGEX.captures_iter(data).enumerate() {
+            let captured_match = captures.get(0).unwrap();
+            output.push_str(&data[position..captured_match.start()]);
+            position = captured_match.end();
+
+            match FunctionCall::parse(&captures["call"]) {
+                Some(function_call) => {
+                    let function_call = function_call?;
+                    let key = format!("{}:{}:{}", name, index, function_call.get_key());
+                    output.push_str(&self.generate(&function_call, &key)?);
+                },
+                None => output.push_str(captured_match.as_str()),
+            }
+        }
+
+        output.push_str(&data[position..]);
+        Ok(output)
+    }
+}
+
+/// Calculates the FNV-1a hash, which stays the same between runs and builds.
+fn get_stable_hash(data: &str) -> u64 {
+    data.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Checks whether the expression looks like the call of the generator function.
+pub fn is_function_call(expression: &str) -> bool {
+    FUNCTION_CALL_REGEX
+        .captures(expression.trim())
+        .is_some_and(|captures| GENERATOR_NAMES.contains(&&captures["name"]))
+}
+
+impl Default for Generators {
+    fn default() -> Self {
+        Generators::new(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(expression: &str) -> FunctionCall {
+        FunctionCall::parse(expression).unwrap().unwrap()
+    }
+
+    fn get_error(expression: &str) -> String {
+        match FunctionCall::parse(expression) {
+            Some(Err(err)) => err.to_string(),
+            _ => panic!("expected the invalid call `{}`", expression),
+        }
+    }
+
+    #[test]
+    fn test_seeded_values_are_reproducible() {
+        let generators = Generators::new(Some(1));
+        let uuid = generators.get_value(&call("uuid()")).unwrap();
+        let random_string = generators.get_value(&call("random_string(16)")).unwrap();
+        assert_eq!(uuid.len(), 36);
+        assert_eq!(random_string.len(), 16);
+        assert!(random_string.chars().all(|c| c.is_ascii_alphanumeric()));
+        // The same calls return the same values during the initialization
+        assert_eq!(generators.get_value(&call("uuid( )")).unwrap(), uuid);
+
+        // The values depend only on the seed, but not on the order of the calls
+        let other_generators = Generators::new(Some(1));
+        assert_eq!(other_generators.get_value(&call("random_string(16)")).unwrap(), random_string);
+        assert_eq!(other_generators.get_value(&call("uuid()")).unwrap(), uuid);
+        assert_ne!(Generators::new(Some(2)).get_value(&call("uuid()")).unwrap(), uuid);
+    }
+
+    #[test]
+    fn test_invalid_calls() {
+        assert!(FunctionCall::parse("hashFiles('**/Cargo.lock')").is_none());
+        assert!(FunctionCall::parse("uuid").is_none());
+        assert_eq!(get_error("uuid(1)"), "the `uuid` function expects 0 argument(s), but got 1");
+        assert_eq!(get_error("random_port(1)"), "the `random_port` function expects 0 or 2 argument(s), but got 1");
+        assert_eq!(get_error("random_string(n)"), "the `random_string` function expects numeric arguments, but got `n`");
+        assert_eq!(get_error("random_string(0)"), "the length of the random string must be in the range from 1 to 4096");
+        assert_eq!(get_error("random_port(10, 5)"), "invalid range of ports from 10 to 5");
+        assert_eq!(get_error("random_port(1024, 70000)"), "invalid range of ports from 1024 to 70000");
+    }
+
+    #[test]
+    fn test_render_calls() {
+        let generators = Generators::new(Some(1));
+        let first_value = generators.render_calls("{{ random_string(8) }}", "storage.variables.a").unwrap();
+        let second_value = generators.render_calls("{{ random_string(8) }}", "storage.variables.b").unwrap();
+        assert_eq!(first_value.len(), 8);
+        // Each storage variable gets its own value
+        assert_ne!(first_value, second_value);
+        assert_eq!(generators.render_calls("{{ random_string(8) }}", "storage.variables.a").unwrap(), first_value);
+
+        let value = generators.render_calls("{{uuid()}}/{{ Vars.name }}", "id").unwrap();
+        assert_eq!(value.len(), 36 + "/{{ Vars.name }}".len());
+        assert!(value.ends_with("/{{ Vars.name }}"));
+        assert!(generators.render_calls("{{ random_string(0) }}", "a").is_err());
+    }
+}
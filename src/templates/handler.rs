@@ -3,6 +3,7 @@ use std::path::PathBuf;
 use std::process::{Command, Stdio};
 use std::time::Instant;
 
+use indexmap::IndexMap;
 use indicatif::HumanDuration;
 use quick_error::ResultExt;
 
@@ -24,15 +25,24 @@ impl Handler {
         &self,
         target_directory_path: &String,
         templates: &HashMap<String, String>,
-        configs: &HashMap<String, Box<Config>>,
+        configs: &IndexMap<String, Box<Config>>,
     ) -> Result<(), Error> {
         let started = Instant::now();
+        self.show_templates(configs);
         let project_directory_path = PathBuf::from(target_directory_path);
+        let tasks: Vec<(&String, Task)> = configs
+            .iter()
+            .map(|(template_name, config)| {
+                let template_directory_path = PathBuf::from(templates.get(template_name).unwrap());
+                let task = Task::new(&project_directory_path, &template_directory_path, config)?;
+                Ok((template_name, task))
+            })
+            .collect::<Result<Vec<(&String, Task)>, Error>>()?;
+
+        self.check_unresolved_placeholders(&tasks)?;
         create_directory(&project_directory_path)?;
 
-        for (template_name, config) in configs {
-            let template_directory_path = PathBuf::from(templates.get(template_name).unwrap());
-            let task = Task::new(&project_directory_path, &template_directory_path, config);
+        for (_template_name, task) in tasks.iter() {
             task.run()?;
         }
 
@@ -45,7 +55,7 @@ impl Handler {
             for after_init_hook in scripts {
                 let mut command: Vec<String> = after_init_hook
                     .split_ascii_whitespace()
-                    .map(|slice| String::from(slice))
+                    .map(String::from)
                     .collect();
 
                 if command.is_empty() {
@@ -73,4 +83,47 @@ impl Handler {
         );
         Ok(())
     }
+
+    /// Prints the used templates in the order of the execution with the details
+    /// from the config[meta] space.
+    fn show_templates(&self, configs: &IndexMap<String, Box<Config>>) {
+        println!("Initializing the project with the following templates:");
+        for (template_name, config) in configs.iter() {
+            match &config.json_config.meta {
+                Some(meta) => {
+                    println!("  {} - {}", template_name, meta.get_title(template_name));
+                    if let Some(description) = &meta.description {
+                        println!("    {}", description);
+                    }
+                },
+                None => println!("  {}", template_name),
+            }
+        }
+    }
+
+    /// Stops the initialization before any changes on the disk if any of
+    /// the strict templates has got unresolved placeholders.
+    fn check_unresolved_placeholders(&self, tasks: &Vec<(&String, Task)>) -> Result<(), Error> {
+        let mut messages: Vec<String> = Vec::new();
+        for (template_name, task) in tasks.iter().filter(|(_template_name, task)| task.is_strict()) {
+            let report = task.find_unresolved_placeholders()?;
+            if !report.is_empty() {
+                messages.push(format!(
+                    "The `{}` template has {} unresolved placeholder(s):\n  {}",
+                    template_name, report.len(), report.join("\n  ")
+                ));
+            }
+        }
+
+        match messages.is_empty() {
+            true => Ok(()),
+            false => {
+                let message = format!(
+                    "Strict mode: the project wasn't initialized.\n{}",
+                    messages.join("\n")
+                );
+                Err(Error::Other(message))
+            }
+        }
+    }
 }
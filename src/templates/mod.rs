@@ -1,11 +1,22 @@
+pub mod builtins;
+pub mod conditions;
 pub mod config;
+pub mod engine;
+pub mod filters;
+pub mod generators;
 pub mod handler;
+pub mod parser;
 pub mod renderer;
 pub mod task;
+pub mod tera_engine;
 pub mod utils;
+pub mod variables;
 
+pub use self::builtins::Builtins;
 pub use self::config::{Config, get_template_configs};
+pub use self::engine::TemplateEngine;
+pub use self::generators::Generators;
 pub use self::handler::Handler;
-pub use self::renderer::{TemplateRenreder, TEMPLATE_VARIABLE_REGEX};
-pub use self::task::Task;
-pub use self::utils::{is_correct_template_list, generate_subcontexts};
+pub use self::renderer::TemplateRenreder;
+pub use self::tera_engine::TeraEngine;
+pub use self::utils::is_correct_template_list;
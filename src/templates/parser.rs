@@ -0,0 +1,688 @@
+use std::collections::BTreeSet;
+
+use lazy_static::lazy_static;
+use regex::{escape, Regex};
+
+use crate::error::Error;
+use crate::templates::conditions::Condition;
+use crate::templates::config::DelimitersConfig;
+use crate::templates::filters::{Placeholder, split_outside_quotes};
+
+lazy_static! {
+    static ref INCLUDE_REGEX: Regex = Regex::new(r#"^(?:"(?P<double>[^"]+)"|'(?P<single>[^']+)')$"#).unwrap();
+    static ref LOOP_REGEX: Regex = Regex::new(r"^(?P<item>[\w-]+)\s+in\s+(?P<name>[\w.-]+)$").unwrap();
+}
+
+/// Kind of the delimiters, found by the tokenizer.
+enum DelimiterKind {
+    Variable,
+    Block,
+    Comment,
+}
+
+/// Lexical piece of the template, produced by the tokenizer.
+enum Token {
+    Text(String),
+    Variable {
+        source: String,
+        expression: String,
+        line: usize,
+        trim_left: bool,
+        trim_right: bool,
+    },
+    Tag {
+        source: String,
+        keyword: String,
+        expression: String,
+        line: usize,
+    },
+}
+
+/// Node of the compiled template.
+pub enum Node {
+    Text(String),
+    Variable(Variable),
+    Condition(Vec<Branch>),
+    Loop(Iteration),
+    Include(String, usize),
+}
+
+/// The `{{ name | filter }}` placeholder. Placeholders with the invalid
+/// expression keep the error message, because they can be unrelated to the
+/// template context and must be left as is in this case. The `-` trim markers
+/// are applied only when the placeholder is rendered.
+pub struct Variable {
+    pub source: String,
+    pub variable_name: String,
+    pub line: usize,
+    pub placeholder: Result<Placeholder, String>,
+    pub trim_left: bool,
+    pub trim_right: bool,
+}
+
+/// One of the `if` / `elif` / `else` branches of the conditional block.
+/// The `else` branch doesn't have any condition.
+pub struct Branch {
+    pub condition: Option<Condition>,
+    pub nodes: Vec<Node>,
+}
+
+/// Loop over the array variable. Each iteration of the loop is rendered
+/// with the current item, available under the `item_name` key.
+pub struct Iteration {
+    pub item_name: String,
+    pub variable_name: String,
+    pub nodes: Vec<Node>,
+}
+
+/// Block that was opened, but not closed yet by the `endif` or `endfor` tags.
+enum OpenedBlock {
+    Condition {
+        line: usize,
+        branches: Vec<Branch>,
+        has_else_branch: bool,
+    },
+    Loop {
+        line: usize,
+        iteration: Iteration,
+    },
+}
+
+/// Template, compiled into the tree of nodes. Can be rendered any number of
+/// times with different contexts without parsing the template again.
+pub struct CompiledTemplate {
+    pub nodes: Vec<Node>,
+}
+
+impl CompiledTemplate {
+    /// Returns names and lines of the templates included by the `{% include %}`
+    /// tags, including the tags inside of the conditional blocks and loops.
+    pub fn get_includes(&self) -> Vec<(String, usize)> {
+        let mut includes = Vec::new();
+        collect_includes(&self.nodes, &mut includes);
+        includes
+    }
+
+    /// Returns names of the variables used in the placeholders of the template.
+    /// Variables of the included templates aren't the part of the result.
+    pub fn get_variables(&self) -> BTreeSet<String> {
+        let mut variables = BTreeSet::new();
+        collect_variables(&self.nodes, &mut variables);
+        variables
+    }
+}
+
+/// Compiles templates in according to the used delimiters.
+pub struct TemplateParser {
+    variable_delimiters: (String, String),
+    block_delimiters: (String, String),
+    comment_delimiters: (String, String),
+    end_raw_tag_regex: Regex,
+}
+
+impl TemplateParser {
+    pub fn from_delimiters(delimiters: &DelimitersConfig) -> Self {
+        let (block_start, block_end) = delimiters.get_block_delimiters();
+        TemplateParser {
+            end_raw_tag_regex: Regex::new(&format!(
+                r"{}(?P<left>-)?\s*endraw\s*(?P<right>-)?{}",
+                escape(&block_start), escape(&block_end)
+            )).unwrap(),
+            variable_delimiters: delimiters.get_variable_delimiters(),
+            block_delimiters: (block_start, block_end),
+            comment_delimiters: delimiters.get_comment_delimiters(),
+        }
+    }
+
+    /// Compiles the template into the tree of text, placeholder, conditional,
+    /// loop and include nodes.
+    pub fn compile(&self, data: &str) -> Result<CompiledTemplate, Error> {
+        let tokens = self.tokenize(data)?;
+        let nodes = self.parse(tokens)?;
+        Ok(CompiledTemplate { nodes })
+    }
+
+    /// Splits the template onto text, placeholders and tags in a single pass.
+    /// Content of the `{% raw %}` blocks and the placeholders or tags prefixed
+    /// with the backslash are returned as the text, while comments are skipped.
+    /// Like placeholders, comments must be closed on the same line, so that
+    /// `{#` and `#}` in shell scripts (e.g. `${#}`) are kept as the text.
+    /// The `-` trim markers of tags and comments strip whitespaces from the
+    /// adjacent text, while placeholders keep them until rendering.
+    fn tokenize(&self, data: &str) -> Result<Vec<Token>, Error> {
+        let delimiters = [
+            (DelimiterKind::Variable, &self.variable_delimiters),
+            (DelimiterKind::Block, &self.block_delimiters),
+            (DelimiterKind::Comment, &self.comment_delimiters),
+        ];
+        let mut start_matches: Vec<Option<CachedMatch>> = delimiters.iter().map(|_| None).collect();
+        let mut end_matches: Vec<Option<CachedMatch>> = delimiters.iter().map(|_| None).collect();
+        let mut tokens = Vec::new();
+        let mut text_start = 0;
+        let mut position = 0;
+        let mut line = 1;
+        let mut line_position = 0;
+        let mut trim_next_text = false;
+
+        loop {
+            let next_delimiter = delimiters
+                .iter()
+                .enumerate()
+                .filter_map(|(index, (kind, (start_delimiter, end_delimiter)))| {
+                    find_cached(data, start_delimiter, position, &mut start_matches[index])
+                        .map(|start| (start, index, kind, start_delimiter, end_delimiter))
+                })
+                .min_by_key(|(start, _, _, _, _)| *start);
+            let (start, index, kind, start_delimiter, end_delimiter) = match next_delimiter {
+                Some(delimiter) => delimiter,
+                None => break,
+            };
+
+            // Delimiters without the closing pair or with a multiline content are just a text
+            let content_start = start + start_delimiter.len();
+            let content_end = match find_cached(data, end_delimiter, content_start, &mut end_matches[index]) {
+                Some(content_end) => content_end,
+                None => {
+                    position = content_start;
+                    continue
+                }
+            };
+            let (trim_left, content, trim_right) = split_trim_markers(&data[content_start..content_end]);
+            let is_valid = match kind {
+                DelimiterKind::Variable => !content.is_empty() && !content.contains('\n'),
+                DelimiterKind::Block => !content.trim().contains('\n'),
+                DelimiterKind::Comment => !content.contains('\n'),
+            };
+            if !is_valid {
+                position = content_start;
+                continue
+            }
+
+            let end = content_end + end_delimiter.len();
+            let source = &data[start..end];
+            line += data[line_position..start].matches('\n').count();
+            line_position = start;
+
+            let is_escaped = data[..start].ends_with('\\');
+            let text_end = match is_escaped {
+                true => start - 1,
+                false => start,
+            };
+            let is_trimmed = !is_escaped && !matches!(kind, DelimiterKind::Variable);
+            push_text(&mut tokens, &data[text_start..text_end], trim_next_text, trim_left && is_trimmed);
+            trim_next_text = trim_right && is_trimmed;
+            text_start = end;
+            position = end;
+
+            if is_escaped {
+                tokens.push(Token::Text(source.to_string()));
+                continue
+            }
+
+            match kind {
+                DelimiterKind::Comment => continue,
+                DelimiterKind::Variable => {
+                    tokens.push(Token::Variable {
+                        source: source.to_string(),
+                        expression: content.trim().to_string(),
+                        line,
+                        trim_left,
+                        trim_right,
+                    });
+                    continue
+                },
+                DelimiterKind::Block => {},
+            }
+
+            let tag = content.trim();
+            let (keyword, expression) = match tag.find(char::is_whitespace) {
+                Some(index) => (&tag[..index], tag[index..].trim()),
+                None => (tag, ""),
+            };
+
+            if keyword == "raw" {
+                match self.end_raw_tag_regex.captures(&data[position..]) {
+                    Some(end_tag_captures) => {
+                        let end_tag_match = end_tag_captures.get(0).unwrap();
+                        let raw_text = &data[position..position + end_tag_match.start()];
+                        push_text(&mut tokens, raw_text, trim_next_text, end_tag_captures.name("left").is_some());
+                        trim_next_text = end_tag_captures.name("right").is_some();
+                        position += end_tag_match.end();
+                        text_start = position;
+                    },
+                    None => {
+                        let message = "`{% raw %}` block was opened here, but never closed with the `{% endraw %}` tag";
+                        return Err(syntax_error(line, message))
+                    }
+                }
+                continue
+            }
+
+            tokens.push(Token::Tag {
+                source: source.to_string(),
+                keyword: keyword.to_string(),
+                expression: expression.to_string(),
+                line,
+            });
+        }
+
+        push_text(&mut tokens, &data[text_start..], trim_next_text, false);
+        Ok(tokens)
+    }
+
+    /// Builds the tree of nodes from the tokens in according to the `{% if %}`,
+    /// `{% elif %}`, `{% else %}`, `{% endif %}`, `{% for %}`, `{% endfor %}`
+    /// and `{% include %}` tags.
+    fn parse(&self, tokens: Vec<Token>) -> Result<Vec<Node>, Error> {
+        let mut root: Vec<Node> = Vec::new();
+        let mut stack: Vec<OpenedBlock> = Vec::new();
+
+        for token in tokens {
+            let (source, keyword, expression, line) = match token {
+                Token::Text(text) => {
+                    push_node(&mut root, &mut stack, Node::Text(text));
+                    continue
+                },
+                Token::Variable { source, expression, line, trim_left, trim_right } => {
+                    let variable = Variable {
+                        variable_name: split_outside_quotes(&expression, '|')[0].trim().to_string(),
+                        placeholder: Placeholder::parse(&expression).map_err(|err| err.to_string()),
+                        source,
+                        line,
+                        trim_left,
+                        trim_right,
+                    };
+                    push_node(&mut root, &mut stack, Node::Variable(variable));
+                    continue
+                },
+                Token::Tag { source, keyword, expression, line } => (source, keyword, expression, line),
+            };
+            let expression = expression.as_str();
+
+            match keyword.as_str() {
+                "if" => {
+                    let condition = parse_condition(expression, &keyword, line)?;
+                    stack.push(OpenedBlock::Condition {
+                        line,
+                        branches: vec![Branch { condition: Some(condition), nodes: Vec::new() }],
+                        has_else_branch: false,
+                    });
+                },
+                "elif" => {
+                    let condition = parse_condition(expression, &keyword, line)?;
+                    match stack.last_mut() {
+                        Some(OpenedBlock::Condition { branches, has_else_branch: false, .. }) => {
+                            branches.push(Branch { condition: Some(condition), nodes: Vec::new() });
+                        },
+                        Some(OpenedBlock::Condition { .. }) => {
+                            return Err(syntax_error(line, "`{% elif %}` tag can't follow the `{% else %}` tag"))
+                        },
+                        _ => return Err(syntax_error(line, "`{% elif %}` tag without the matching `{% if %}` tag")),
+                    }
+                },
+                "else" => {
+                    match stack.last_mut() {
+                        Some(OpenedBlock::Condition { branches, has_else_branch, .. }) if !*has_else_branch => {
+                            *has_else_branch = true;
+                            branches.push(Branch { condition: None, nodes: Vec::new() });
+                        },
+                        Some(OpenedBlock::Condition { .. }) => {
+                            return Err(syntax_error(line, "`{% if %}` block can have only one `{% else %}` tag"))
+                        },
+                        _ => return Err(syntax_error(line, "`{% else %}` tag without the matching `{% if %}` tag")),
+                    }
+                },
+                "endif" => {
+                    match stack.pop() {
+                        Some(OpenedBlock::Condition { branches, .. }) => {
+                            push_node(&mut root, &mut stack, Node::Condition(branches));
+                        },
+                        Some(OpenedBlock::Loop { line: opened_line, .. }) => {
+                            let message = format!(
+                                "`{{% endif %}}` tag can't close the `{{% for %}}` block opened at line {}",
+                                opened_line
+                            );
+                            return Err(syntax_error(line, &message))
+                        },
+                        None => return Err(syntax_error(line, "`{% endif %}` tag without the matching `{% if %}` tag")),
+                    }
+                },
+                "for" => {
+                    let loop_captures = match LOOP_REGEX.captures(expression) {
+                        Some(loop_captures) => loop_captures,
+                        None => {
+                            let message = format!(
+                                "invalid loop `{}` in the `{{% for %}}` tag. Expected \
+                                the `item in variable` expression",
+                                expression
+                            );
+                            return Err(syntax_error(line, &message))
+                        }
+                    };
+
+                    stack.push(OpenedBlock::Loop {
+                        line,
+                        iteration: Iteration {
+                            item_name: loop_captures["item"].to_string(),
+                            variable_name: loop_captures["name"].to_string(),
+                            nodes: Vec::new(),
+                        },
+                    });
+                },
+                "endfor" => {
+                    match stack.pop() {
+                        Some(OpenedBlock::Loop { iteration, .. }) => {
+                            push_node(&mut root, &mut stack, Node::Loop(iteration));
+                        },
+                        Some(OpenedBlock::Condition { line: opened_line, .. }) => {
+                            let message = format!(
+                                "`{{% endfor %}}` tag can't close the `{{% if %}}` block opened at line {}",
+                                opened_line
+                            );
+                            return Err(syntax_error(line, &message))
+                        },
+                        None => return Err(syntax_error(line, "`{% endfor %}` tag without the matching `{% for %}` tag")),
+                    }
+                },
+                "include" => {
+                    match INCLUDE_REGEX.captures(expression) {
+                        Some(include_captures) => {
+                            let name = include_captures.name("double")
+                                .or(include_captures.name("single"))
+                                .unwrap()
+                                .as_str();
+                            push_node(&mut root, &mut stack, Node::Include(name.to_string(), line));
+                        },
+                        None => {
+                            let message = format!(
+                                "invalid template name `{}` in the `{{% include %}}` tag. \
+                                Expected the quoted path or the name of the template",
+                                expression
+                            );
+                            return Err(syntax_error(line, &message))
+                        }
+                    }
+                },
+                "endraw" => return Err(syntax_error(line, "`{% endraw %}` tag without the matching `{% raw %}` tag")),
+                _ => {
+                    let message = format!("unknown `{}` tag", source);
+                    return Err(syntax_error(line, &message))
+                }
+            }
+        }
+
+        match stack.last() {
+            Some(OpenedBlock::Condition { line, .. }) => {
+                let message = "`{% if %}` block was opened here, but never closed with the `{% endif %}` tag";
+                Err(syntax_error(*line, message))
+            },
+            Some(OpenedBlock::Loop { line, .. }) => {
+                let message = "`{% for %}` block was opened here, but never closed with the `{% endfor %}` tag";
+                Err(syntax_error(*line, message))
+            },
+            None => Ok(root),
+        }
+    }
+}
+
+/// Position of the delimiter, found by the search from the `from` position.
+/// `None` means that the delimiter doesn't occur after the `from` position.
+#[derive(Clone, Copy)]
+struct CachedMatch {
+    from: usize,
+    found: Option<usize>,
+}
+
+/// Finds the next occurrence of the delimiter, starting from the position.
+/// The previous result is reused while the position doesn't move past it,
+/// so that the text is scanned for each delimiter only once in total.
+fn find_cached(data: &str, delimiter: &str, position: usize, cache: &mut Option<CachedMatch>) -> Option<usize> {
+    if let Some(cached_match) = cache {
+        let is_actual = cached_match.from <= position &&
+            cached_match.found.is_none_or(|found| found >= position);
+        if is_actual {
+            return cached_match.found
+        }
+    }
+
+    let found = data[position..].find(delimiter).map(|index| position + index);
+    *cache = Some(CachedMatch { from: position, found });
+    found
+}
+
+/// Strips the `-` trim markers from the content of the placeholder, the tag
+/// or the comment. Returns the flags whether the whitespaces before and after
+/// must be removed.
+fn split_trim_markers(content: &str) -> (bool, &str, bool) {
+    let trim_left = content.starts_with('-');
+    let content = match trim_left {
+        true => &content[1..],
+        false => content,
+    };
+    let trim_right = content.ends_with('-');
+    let content = match trim_right {
+        true => &content[..content.len() - 1],
+        false => content,
+    };
+    (trim_left, content, trim_right)
+}
+
+/// Adds the non-empty text token, optionally stripping the whitespaces.
+fn push_text(tokens: &mut Vec<Token>, text: &str, trim_start: bool, trim_end: bool) {
+    let text = match trim_start {
+        true => text.trim_start(),
+        false => text,
+    };
+    let text = match trim_end {
+        true => text.trim_end(),
+        false => text,
+    };
+    if !text.is_empty() {
+        tokens.push(Token::Text(text.to_string()));
+    }
+}
+
+/// Stores the parsed node in the innermost opened block, or in the root.
+fn push_node(root: &mut Vec<Node>, stack: &mut [OpenedBlock], node: Node) {
+    match stack.last_mut() {
+        Some(OpenedBlock::Condition { branches, .. }) => branches.last_mut().unwrap().nodes.push(node),
+        Some(OpenedBlock::Loop { iteration, .. }) => iteration.nodes.push(node),
+        None => root.push(node),
+    }
+}
+
+/// Parses the condition of the `if` or `elif` tags.
+fn parse_condition(expression: &str, keyword: &str, line: usize) -> Result<Condition, Error> {
+    Condition::parse(expression).map_err(|err| {
+        let message = format!(
+            "invalid condition `{}` in the `{{% {} %}}` tag: {}",
+            expression, keyword, err
+        );
+        syntax_error(line, &message)
+    })
+}
+
+fn collect_variables(nodes: &[Node], variables: &mut BTreeSet<String>) {
+    for node in nodes {
+        match node {
+            Node::Variable(variable) => {
+                let is_function_call = match &variable.placeholder {
+                    Ok(placeholder) => placeholder.function.is_some(),
+                    Err(_) => false,
+                };
+                if !is_function_call {
+                    variables.insert(variable.variable_name.clone());
+                }
+            },
+            Node::Condition(branches) => {
+                for branch in branches.iter() {
+                    if let Some(condition) = &branch.condition {
+                        variables.extend(condition.get_variable_names());
+                    }
+                    collect_variables(&branch.nodes, variables);
+                }
+            },
+            Node::Loop(iteration) => {
+                variables.insert(iteration.variable_name.clone());
+                collect_variables(&iteration.nodes, variables);
+            },
+            Node::Text(_) | Node::Include(_, _) => {},
+        }
+    }
+}
+
+fn collect_includes(nodes: &[Node], includes: &mut Vec<(String, usize)>) {
+    for node in nodes {
+        match node {
+            Node::Include(name, line) => includes.push((name.to_owned(), *line)),
+            Node::Condition(branches) => {
+                for branch in branches.iter() {
+                    collect_includes(&branch.nodes, includes);
+                }
+            },
+            Node::Loop(iteration) => collect_includes(&iteration.nodes, includes),
+            Node::Text(_) | Node::Variable(_) => {},
+        }
+    }
+}
+
+pub fn syntax_error(line: usize, message: &str) -> Error {
+    Error::Other(format!("Template syntax error at line {}: {}.", line, message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_parser(comment_delimiters: Option<(&str, &str)>) -> TemplateParser {
+        let delimiters = DelimitersConfig {
+            comment: comment_delimiters.map(|(start, end)| (start.to_string(), end.to_string())),
+            ..DelimitersConfig::default()
+        };
+        TemplateParser::from_delimiters(&delimiters)
+    }
+
+    /// Joins the text nodes. Fails on any other node.
+    fn get_text(nodes: &[Node]) -> String {
+        nodes.iter()
+            .map(|node| match node {
+                Node::Text(text) => text.clone(),
+                _ => panic!("expected only text nodes"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_bash_length_expansions_are_text() {
+        let data = "echo \"args: ${#}\"\nset -e\nrm -rf build\necho \"again: ${#}\"\nlen=${#arr[@]}\n#}\n";
+        let template = get_parser(None).compile(data).unwrap();
+        assert_eq!(get_text(&template.nodes), data);
+    }
+
+    #[test]
+    fn test_comments_are_skipped() {
+        let template = get_parser(None).compile("a{# note #}b\n{#- trimmed -#}\nc").unwrap();
+        assert_eq!(get_text(&template.nodes), "abc");
+    }
+
+    #[test]
+    fn test_multiline_comments_are_text() {
+        let data = "line\necho ${#\n#} end\n";
+        let template = get_parser(None).compile(data).unwrap();
+        assert_eq!(get_text(&template.nodes), data);
+    }
+
+    #[test]
+    fn test_custom_comment_delimiters() {
+        let template = get_parser(Some(("<#", "#>"))).compile("a<# note #>b ${#x} {# kept #}").unwrap();
+        assert_eq!(get_text(&template.nodes), "ab ${#x} {# kept #}");
+    }
+
+    #[test]
+    fn test_escaped_comment_is_text() {
+        let template = get_parser(None).compile("\\{# text #}").unwrap();
+        assert_eq!(get_text(&template.nodes), "{# text #}");
+    }
+
+    /// Compiles the template, which must fail, and returns the error message.
+    fn get_error(data: &str) -> String {
+        match get_parser(None).compile(data) {
+            Err(err) => err.to_string(),
+            Ok(_) => panic!("expected the syntax error for `{}`", data),
+        }
+    }
+
+    #[test]
+    fn test_unclosed_if_is_error() {
+        let message = get_error("a\n{% if flag %}\nb\n");
+        assert!(message.contains("at line 2") && message.contains("`{% endif %}`"), "{}", message);
+    }
+
+    #[test]
+    fn test_misplaced_condition_tags_are_errors() {
+        assert!(get_error("{% endif %}").contains("without the matching `{% if %}`"));
+        assert!(get_error("{% else %}").contains("without the matching `{% if %}`"));
+        assert!(get_error("{% if a %}{% else %}{% elif b %}{% endif %}").contains("can't follow"));
+        assert!(get_error("{% if a %}{% else %}{% else %}{% endif %}").contains("only one `{% else %}`"));
+    }
+
+    #[test]
+    fn test_unclosed_for_is_error() {
+        let message = get_error("{% for item in items %}\n{% if item %}{{ item }}{% endif %}\n");
+        assert!(message.contains("at line 1") && message.contains("`{% endfor %}`"), "{}", message);
+    }
+
+    #[test]
+    fn test_variables_include_loop_variables() {
+        let template = get_parser(None).compile("{% for env in envs %}[{{ env }}]{% endfor %}").unwrap();
+        let variables: Vec<String> = template.get_variables().into_iter().collect();
+        assert_eq!(variables, vec!["env".to_string(), "envs".to_string()]);
+    }
+
+    #[test]
+    fn test_misplaced_loop_tags_are_errors() {
+        assert!(get_error("{% endfor %}").contains("without the matching `{% for %}`"));
+        assert!(get_error("{% for item in items %}{% endif %}").contains("at line 1"));
+    }
+
+    #[test]
+    fn test_unclosed_raw_is_error() {
+        let message = get_error("a\nb\n{% raw %}{{ name }}\n");
+        assert!(message.contains("at line 3"), "{}", message);
+        assert!(get_error("{% endraw %}").contains("without the matching `{% raw %}`"));
+    }
+
+    #[test]
+    fn test_raw_block_is_text() {
+        let template = get_parser(None).compile("a{% raw %}{{ name }}{% if %}{% endraw %}b").unwrap();
+        assert_eq!(get_text(&template.nodes), "a{{ name }}{% if %}b");
+    }
+
+    #[test]
+    fn test_escaped_placeholders_are_text() {
+        let template = get_parser(None).compile("\\{{ name }} \\{% if flag %}").unwrap();
+        assert_eq!(get_text(&template.nodes), "{{ name }} {% if flag %}");
+    }
+
+    #[test]
+    fn test_multiline_placeholders_are_text() {
+        let template = get_parser(None).compile("{{ a\nb }} {{ unclosed").unwrap();
+        assert_eq!(get_text(&template.nodes), "{{ a\nb }} {{ unclosed");
+    }
+
+    #[test]
+    fn test_variables_skip_function_calls() {
+        let template = get_parser(None).compile("{{ name | upper }}{{ uuid() }}{{ random_string(8) }}").unwrap();
+        let variables: Vec<String> = template.get_variables().into_iter().collect();
+        assert_eq!(variables, vec!["name".to_string()]);
+    }
+
+    #[test]
+    fn test_trim_markers_strip_whitespaces() {
+        let template = get_parser(None).compile("a  \n{%- raw -%}\n  b  \n{%- endraw -%}\n  c").unwrap();
+        assert_eq!(get_text(&template.nodes), "abc");
+
+        let template = get_parser(None).compile("a  \n{%- raw %} b {% endraw -%}\n  c").unwrap();
+        assert_eq!(get_text(&template.nodes), "a b c");
+    }
+}
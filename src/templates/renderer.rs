@@ -1,79 +1,548 @@
-use std::collections::HashMap;
-use std::io::Write;
-use std::fs::{File, read_to_string};
-use std::path::PathBuf;
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
 
-use lazy_static::lazy_static;
-use quick_error::ResultExt;
-use regex::Regex;
-use serde_json::Value as SerdeValue;
+use regex::{escape, Regex};
+use serde_json::{json, Value as SerdeValue};
 
 use crate::error::Error;
+use crate::filesystem::read_text_file;
+use crate::templates::config::{DelimitersConfig, TemplateEntry};
+use crate::templates::engine::TemplateEngine;
+use crate::templates::generators::{Generators, is_function_call};
+use crate::templates::parser::{
+    CompiledTemplate, Iteration, Node, TemplateParser, Variable, syntax_error,
+};
+use crate::templates::utils::get_template_variables;
 
-lazy_static! {
-    pub static ref TEMPLATE_VARIABLE_REGEX: Regex = Regex::new(r"(?P<var>\{{2}(?P<name>.{1,}?)\}{2})").unwrap();
+/// Template file in the chain of includes, started from the rendered template.
+struct IncludedTemplate {
+    name: String,
+    path: PathBuf,
 }
 
+/// Placeholder that wasn't resolved from the context during rendering.
+/// The file is specified only for placeholders from the included templates.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UnresolvedPlaceholder {
+    pub file: Option<String>,
+    pub line: usize,
+    pub name: String,
+}
+
+/// Output of the rendered template with the placeholders which were left as is.
+#[derive(Debug, Default)]
+pub struct RenderedTemplate {
+    pub data: String,
+    pub unresolved: Vec<UnresolvedPlaceholder>,
+}
 
-pub struct TemplateRenreder;
+pub struct TemplateRenreder {
+    variable_regex: Regex,
+    parser: TemplateParser,
+    template_directory_path: PathBuf,
+    partials: HashMap<String, String>,
+    generators: Arc<Generators>,
+    compiled_templates: RefCell<HashMap<PathBuf, Rc<CompiledTemplate>>>,
+}
 
 impl TemplateRenreder {
-    pub fn new() -> Self {
-        TemplateRenreder {}
+    /// Creates the renderer that uses the given delimiters for the placeholders
+    /// and the tags instead of the default `{{ }}` and `{% %}` ones.
+    pub fn from_delimiters(delimiters: &DelimitersConfig) -> Self {
+        let (variable_start, variable_end) = delimiters.get_variable_delimiters();
+        TemplateRenreder {
+            variable_regex: Regex::new(&format!(
+                r"(?P<var>{}(?P<name>.{{1,}}?){})",
+                escape(&variable_start), escape(&variable_end)
+            )).unwrap(),
+            parser: TemplateParser::from_delimiters(delimiters),
+            template_directory_path: PathBuf::new(),
+            partials: HashMap::new(),
+            generators: Arc::new(Generators::default()),
+            compiled_templates: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Resolves included templates relatively to the given template directory.
+    pub fn with_template_directory(mut self, template_directory_path: &PathBuf) -> Self {
+        self.template_directory_path = template_directory_path.to_owned();
+        self
+    }
+
+    /// Resolves included templates by the keys of the config[files][templates] space.
+    pub fn with_partials(mut self, templates: &HashMap<String, TemplateEntry>) -> Self {
+        self.partials = templates
+            .iter()
+            .map(|(name, entry)| (name.to_owned(), entry.get_path().to_owned()))
+            .collect();
+        self
+    }
+
+    /// Uses the shared generators, so that function calls return the same
+    /// values in all templates.
+    pub fn with_generators(mut self, generators: &Arc<Generators>) -> Self {
+        self.generators = generators.clone();
+        self
+    }
+
+    /// Compiles the template file or returns the already compiled one, so that
+    /// the template is parsed only once for all subcontexts. Errors are prefixed
+    /// with the path to the template.
+    fn compile_file(&self, template_path: &Path) -> Result<Rc<CompiledTemplate>, Error> {
+        self.get_compiled_template(template_path)
+            .map_err(|err| add_template_path(err, &template_path.display().to_string()))
+    }
+
+    fn get_compiled_template(&self, template_path: &Path) -> Result<Rc<CompiledTemplate>, Error> {
+        if let Some(template) = self.compiled_templates.borrow().get(template_path) {
+            return Ok(template.clone())
+        }
+
+        let data = read_text_file(template_path)?;
+        let template = Rc::new(self.parser.compile(&data)?);
+        self.compiled_templates.borrow_mut().insert(template_path.to_owned(), template.clone());
+        Ok(template)
+    }
+
+    /// Writes into the output the text and the placeholders, the branches of the
+    /// conditional blocks which conditions are met, the body of loops for each
+    /// item and the included templates.
+    fn evaluate_nodes(
+        &self,
+        nodes: &[Node],
+        context: &SerdeValue,
+        output: &mut RenderedTemplate,
+        includes: &mut Vec<IncludedTemplate>,
+    ) -> Result<(), Error> {
+        // The trim markers of placeholders affect only the adjacent text nodes
+        let mut trim_next_text = false;
+        let mut trailing_whitespaces = 0;
+        for node in nodes {
+            match node {
+                Node::Text(text) => {
+                    let text = match trim_next_text {
+                        true => text.trim_start(),
+                        false => text,
+                    };
+                    output.data.push_str(text);
+                    trailing_whitespaces = text.len() - text.trim_end().len();
+                    trim_next_text = false;
+                    continue
+                },
+                Node::Variable(variable) => {
+                    let position = output.data.len();
+                    let is_rendered = self.render_variable(variable, context, output, includes)?;
+                    if is_rendered && variable.trim_left {
+                        output.data.replace_range(position - trailing_whitespaces..position, "");
+                    }
+                    trim_next_text = is_rendered && variable.trim_right;
+                    trailing_whitespaces = 0;
+                    continue
+                },
+                Node::Condition(branches) => {
+                    let used_branch = branches
+                        .iter()
+                        .find(|branch| match &branch.condition {
+                            Some(condition) => condition.evaluate(context),
+                            None => true,
+                        });
+
+                    if let Some(branch) = used_branch {
+                        self.evaluate_nodes(&branch.nodes, context, output, includes)?;
+                    }
+                },
+                Node::Loop(iteration) => self.evaluate_loop(iteration, context, output, includes)?,
+                Node::Include(name, line) => self.evaluate_include(name, *line, context, output, includes)?,
+            }
+
+            trim_next_text = false;
+            trailing_whitespaces = 0;
+        }
+
+        Ok(())
     }
 
-    /// Generates new file based on the template with specified context.
-    pub fn generate_file_from_template(
+    /// Renders the loop body for each item of the array variable. A string
+    /// variable is treated as the array with a single item, so that loops keep
+    /// working for paths with a unique subcontext. Inside of the loop are
+    /// available the `loop.index`, `loop.index0`, `loop.first`, `loop.last`
+    /// and `loop.length` helpers.
+    fn evaluate_loop(
         &self,
+        iteration: &Iteration,
         context: &SerdeValue,
-        template_path: &PathBuf,
-        out_file_path: &PathBuf,
+        output: &mut RenderedTemplate,
+        includes: &mut Vec<IncludedTemplate>,
     ) -> Result<(), Error> {
-        let template = read_to_string(template_path).context(template_path)?;
-        let data = self.render_template(&template, context);
-        let mut file = File::create(out_file_path).context(out_file_path)?;
-        file.write_all(data.as_bytes()).context(out_file_path)?;
+        let items = match get_context_value(context, &iteration.variable_name) {
+            Some(SerdeValue::Array(values)) => values.clone(),
+            Some(SerdeValue::Null) | None => Vec::new(),
+            Some(value) => vec![value.clone()],
+        };
+
+        let length = items.len();
+        for (index, item) in items.into_iter().enumerate() {
+            let mut loop_context = context.clone();
+            if let Some(map) = loop_context.as_object_mut() {
+                map.insert(iteration.item_name.clone(), item);
+                map.insert("loop".to_string(), json!({
+                    "index": index + 1,
+                    "index0": index,
+                    "first": index == 0,
+                    "last": index + 1 == length,
+                    "length": length,
+                }));
+            }
+            self.evaluate_nodes(&iteration.nodes, &loop_context, output, includes)?;
+        }
+
         Ok(())
     }
 
-    /// Renders string from the given data and the context.
-    pub fn render_template(&self, data: &String, context: &SerdeValue) -> String {
-        let mut template = data.to_owned();
-        let template_context = self.convert_context_into_hashmap(context);
-
-        TEMPLATE_VARIABLE_REGEX.captures_iter(&template.clone())
-            .map(|captures| {
-                let captured_block = captures["var"].to_string().trim().to_string();
-                let variable_name = captures["name"].trim().to_string();
-                (captured_block, variable_name)
-            })
-            .filter(|(_captured_block, variable_name)| {
-                template_context.contains_key(variable_name)
-            })
-            .for_each(|(captured_block, variable_name)| {
-                let replacement = template_context.get(&variable_name).unwrap();
-                template = template.replace(captured_block.as_str(), replacement);
-            });
-
-        template
-    }
-
-    /// Converts the given serde value object into plain hashmap.
-    /// Any non-string key-value pairs will be ignored.
-    fn convert_context_into_hashmap(&self, context: &SerdeValue) -> HashMap<String, String> {
-        let mut template_context: HashMap<String, String> = HashMap::new();
-        context
-            .as_object().unwrap()
+    /// Renders the included template with the same context.
+    fn evaluate_include(
+        &self,
+        name: &String,
+        line: usize,
+        context: &SerdeValue,
+        output: &mut RenderedTemplate,
+        includes: &mut Vec<IncludedTemplate>,
+    ) -> Result<(), Error> {
+        let include_path = self.get_include_path(name, line, includes)?;
+        self.get_compiled_template(&include_path)
+            .and_then(|template| self.evaluate_nodes(&template.nodes, context, output, includes))
+            .map_err(|err| add_template_path(err, name))?;
+        includes.pop();
+        Ok(())
+    }
+
+    /// Collects variables of the template and all templates included by it,
+    /// so that the variables used only in partials are known for the template.
+    fn collect_file_variables(
+        &self,
+        template: &CompiledTemplate,
+        variables: &mut BTreeSet<String>,
+        includes: &mut Vec<IncludedTemplate>,
+    ) -> Result<(), Error> {
+        variables.extend(template.get_variables());
+        for (name, line) in template.get_includes() {
+            let include_path = self.get_include_path(&name, line, includes)?;
+            self.get_compiled_template(&include_path)
+                .and_then(|template| self.collect_file_variables(&template, variables, includes))
+                .map_err(|err| add_template_path(err, &name))?;
+            includes.pop();
+        }
+        Ok(())
+    }
+
+    /// Returns the rendered template as the start of the chain of includes.
+    fn get_included_template(&self, template_path: &Path) -> IncludedTemplate {
+        IncludedTemplate {
+            name: template_path
+                .strip_prefix(&self.template_directory_path)
+                .unwrap_or(template_path)
+                .display()
+                .to_string(),
+            path: template_path.canonicalize().unwrap_or(template_path.to_owned()),
+        }
+    }
+
+    /// Resolves the included template by the key in the config[files][templates]
+    /// space or by the path, relative to the template directory, and pushes it
+    /// into the chain of includes. Fails on include cycles and missing templates.
+    fn get_include_path(
+        &self,
+        name: &String,
+        line: usize,
+        includes: &mut Vec<IncludedTemplate>,
+    ) -> Result<PathBuf, Error> {
+        let relative_path = self.partials.get(name).unwrap_or(name);
+        let include_path = self.template_directory_path.join(relative_path);
+        let canonical_path = include_path.canonicalize().unwrap_or(include_path.clone());
+        let include_chain = includes
             .iter()
-            .for_each(|(key, serde_value)| {
-                match serde_value {
-                    SerdeValue::String(value) => {
-                        template_context.insert(key.to_owned(), value.to_owned());
-                    },
-                    _ => {},
-                };
-            });
-
-        template_context
-    }
-}
\ No newline at end of file
+            .map(|template| template.name.as_str())
+            .chain(vec![name.as_str()])
+            .collect::<Vec<&str>>()
+            .join(" -> ");
+
+        if includes.iter().any(|template| template.path == canonical_path) {
+            let message = format!("include cycle detected: {}", include_chain);
+            return Err(syntax_error(line, &message))
+        }
+
+        if !include_path.is_file() {
+            let message = format!(
+                "the included `{}` template wasn't found at `{}` (include chain: {})",
+                name, include_path.display(), include_chain
+            );
+            return Err(syntax_error(line, &message))
+        }
+
+        includes.push(IncludedTemplate { name: name.to_owned(), path: canonical_path });
+        Ok(include_path)
+    }
+
+    /// Writes the value of the variable from the context, processed by the filters.
+    /// Placeholders with unknown or non-scalar variables are left as is and stored
+    /// as unresolved. Returns whether the placeholder was rendered.
+    fn render_variable(
+        &self,
+        variable: &Variable,
+        context: &SerdeValue,
+        output: &mut RenderedTemplate,
+        includes: &[IncludedTemplate],
+    ) -> Result<bool, Error> {
+        let placeholder = match &variable.placeholder {
+            Ok(placeholder) => placeholder,
+            // Placeholders that aren't related to the context are left as is
+            Err(_) if !is_function_call(&variable.variable_name) && get_context_value(context, &variable.variable_name).is_none() => {
+                output.data.push_str(&variable.source);
+                output.unresolved.push(self.get_unresolved_placeholder(variable, includes));
+                return Ok(false)
+            },
+            Err(err) => {
+                let message = format!("{} in the `{}` placeholder", err, variable.source);
+                return Err(syntax_error(variable.line, &message))
+            }
+        };
+
+        let value = match &placeholder.function {
+            Some(function_call) => Some(json!(self.generators.get_value(function_call)?)),
+            None => get_context_value(context, &placeholder.variable_name).cloned(),
+        };
+        let replacement = placeholder.apply_filters(value)?
+            .as_ref()
+            .and_then(stringify_value);
+
+        match replacement {
+            Some(replacement) => {
+                output.data.push_str(&replacement);
+                Ok(true)
+            },
+            None => {
+                output.data.push_str(&variable.source);
+                output.unresolved.push(self.get_unresolved_placeholder(variable, includes));
+                Ok(false)
+            }
+        }
+    }
+
+    fn get_unresolved_placeholder(&self, variable: &Variable, includes: &[IncludedTemplate]) -> UnresolvedPlaceholder {
+        let file = match includes.len() > 1 {
+            true => includes.last().map(|template| template.name.clone()),
+            false => None,
+        };
+
+        UnresolvedPlaceholder {
+            file,
+            line: variable.line,
+            name: variable.variable_name.clone(),
+        }
+    }
+}
+
+impl TemplateEngine for TemplateRenreder {
+    fn get_template_variables(&self, data: &str) -> BTreeSet<String> {
+        get_template_variables(data, &self.variable_regex)
+    }
+
+    fn get_file_variables(&self, template_path: &Path) -> Result<BTreeSet<String>, Error> {
+        let template = self.compile_file(template_path)?;
+        let mut includes = vec![self.get_included_template(template_path)];
+        let mut variables = BTreeSet::new();
+        self.collect_file_variables(&template, &mut variables, &mut includes)
+            .map_err(|err| add_template_path(err, &template_path.display().to_string()))?;
+        Ok(variables)
+    }
+
+    fn render_file(&self, template_path: &Path, context: &SerdeValue) -> Result<RenderedTemplate, Error> {
+        let template = self.compile_file(template_path)?;
+        let mut includes = vec![self.get_included_template(template_path)];
+        let mut rendered_template = RenderedTemplate::default();
+        self.evaluate_nodes(&template.nodes, context, &mut rendered_template, &mut includes)
+            .map_err(|err| add_template_path(err, &template_path.display().to_string()))?;
+        Ok(rendered_template)
+    }
+
+    fn render_template(&self, data: &str, context: &SerdeValue) -> Result<String, Error> {
+        let template = self.parser.compile(data)?;
+        let mut rendered_template = RenderedTemplate::default();
+        self.evaluate_nodes(&template.nodes, context, &mut rendered_template, &mut Vec::new())?;
+        Ok(rendered_template.data)
+    }
+}
+
+/// Prefixes the error with the path to the template. I/O and encoding errors
+/// already contain the path, so they are returned as is.
+fn add_template_path(err: Error, template_path: &str) -> Error {
+    match err {
+        Error::IoWithContext(_, _) | Error::InvalidEncoding(_) => err,
+        _ => Error::Other(format!("{}: {}", template_path, err)),
+    }
+}
+
+/// Returns the value from the context by the variable name. Nested values
+/// can be accessed by the dotted path, e.g. `loop.index`.
+pub fn get_context_value<'a>(context: &'a SerdeValue, name: &str) -> Option<&'a SerdeValue> {
+    if let Some(value) = context.get(name) {
+        return Some(value)
+    }
+
+    name.split('.')
+        .try_fold(context, |value, key| value.get(key))
+}
+
+/// Converts the scalar value into the string, suitable for the output.
+/// Arrays, objects and null values can't be represented as the plain text.
+pub fn stringify_value(value: &SerdeValue) -> Option<String> {
+    match value {
+        SerdeValue::String(value) => Some(value.to_owned()),
+        SerdeValue::Number(number) => Some(number.to_string()),
+        SerdeValue::Bool(flag) => Some(flag.to_string()),
+        _ => None,
+    }
+}
+
+/// Checks the value for truthiness. Strings are considered as false when they
+/// are empty or equal to the `false`, `no`, `off` or `0` values (case insensitive).
+/// Arrays and objects are false when they are empty.
+pub fn is_truthy_value(value: &SerdeValue) -> bool {
+    match value {
+        SerdeValue::Null => false,
+        SerdeValue::Bool(flag) => *flag,
+        SerdeValue::Number(number) => number.as_f64().map(|value| value != 0.0).unwrap_or(false),
+        SerdeValue::String(value) => {
+            let normalized_value = value.trim().to_lowercase();
+            !matches!(normalized_value.as_str(), "" | "false" | "no" | "off" | "0")
+        },
+        SerdeValue::Array(values) => !values.is_empty(),
+        SerdeValue::Object(map) => !map.is_empty(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn render(data: &str, context: &SerdeValue) -> String {
+        TemplateRenreder::from_delimiters(&DelimitersConfig::default())
+            .render_template(data, context)
+            .unwrap()
+    }
+
+    /// Renders the template and returns the names of unresolved placeholders.
+    fn get_unresolved(data: &str, context: &SerdeValue) -> (String, Vec<String>) {
+        let renderer = TemplateRenreder::from_delimiters(&DelimitersConfig::default());
+        let template = renderer.parser.compile(data).unwrap();
+        let mut output = RenderedTemplate::default();
+        renderer.evaluate_nodes(&template.nodes, context, &mut output, &mut Vec::new()).unwrap();
+        let names = output.unresolved.into_iter().map(|placeholder| placeholder.name).collect();
+        (output.data, names)
+    }
+
+    #[test]
+    fn test_loop_helpers() {
+        let context = json!({"items": ["a", "b", "c"]});
+        let data = "{% for item in items %}{{ loop.index }}/{{ loop.length }}:{{ item }}\
+            {% if loop.first %}^{% endif %}{% if not loop.last %},{% endif %}{% endfor %}";
+        assert_eq!(render(data, &context), "1/3:a^,2/3:b,3/3:c");
+    }
+
+    #[test]
+    fn test_nested_loops_shadow_helpers() {
+        let context = json!({"rows": [["a", "b"], ["c"]]});
+        let data = "{% for row in rows %}{% for cell in row %}{{ loop.index0 }}{{ cell }}{% endfor %};{% endfor %}";
+        assert_eq!(render(data, &context), "0a1b;0c;");
+    }
+
+    #[test]
+    fn test_loop_over_missing_variable_is_empty() {
+        assert_eq!(render("[{% for item in items %}{{ item }}{% endfor %}]", &json!({})), "[]");
+    }
+
+    #[test]
+    fn test_trim_markers_of_unresolved_placeholders_are_ignored() {
+        let data = "spec:\n  {{- if .Values.enabled }}\n  replicas: {{ .Values.replicas }}\n  {{- end }}\n";
+        assert_eq!(render(data, &json!({})), data);
+
+        let data = "a  {{- missing -}}  b";
+        assert_eq!(render(data, &json!({})), data);
+    }
+
+    #[test]
+    fn test_trim_markers_of_placeholders_affect_adjacent_text() {
+        let context = json!({"name": "app", "flag": true});
+        assert_eq!(render("a  \n {{- name -}} \n b", &context), "aappb");
+        assert_eq!(render("a  {% if flag %}{{- name -}}{% endif %}  b", &context), "a  app  b");
+        assert_eq!(render("{{ name -}}  {{- missing }}", &context), "app{{- missing }}");
+        assert_eq!(render("{{ missing -}}  {{- name }}", &context), "{{ missing -}}app");
+    }
+
+    #[test]
+    fn test_unresolved_placeholders_are_kept() {
+        let context = json!({"name": "app"});
+        assert_eq!(render("{{ name }} {{ missing | upper }} ${{ x }}", &context), "app {{ missing | upper }} ${{ x }}");
+    }
+
+    #[test]
+    fn test_unknown_function_calls_are_kept() {
+        let data = "key: ${{ hashFiles('**/Cargo.lock') }}\ntag: ${{ format('{0}-{1}', github.ref, matrix.os) }}\n";
+        let (output, unresolved) = get_unresolved(data, &json!({}));
+        assert_eq!(output, data);
+        assert_eq!(unresolved, vec![
+            "hashFiles('**/Cargo.lock')".to_string(),
+            "format('{0}-{1}', github.ref, matrix.os)".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn test_invalid_generator_calls_are_errors() {
+        let renderer = TemplateRenreder::from_delimiters(&DelimitersConfig::default());
+        for data in ["{{ random_string(0) }}", "{{ uuid(1) }}", "{{ random_port(10, 5) }}"] {
+            assert!(renderer.render_template(data, &json!({})).is_err(), "{}", data);
+        }
+    }
+
+    #[test]
+    fn test_trim_markers_and_escapes() {
+        let context = json!({"name": "app", "flag": true});
+        let data = "a  \n{%- if flag -%}\n  {{- name -}}  \n{%- endif %} \\{{ name }}";
+        assert_eq!(render(data, &context), "aapp {{ name }}");
+    }
+
+    #[test]
+    fn test_file_variables_include_partials() {
+        let directory = tempfile::tempdir().unwrap();
+        fs::create_dir(directory.path().join("partials")).unwrap();
+        fs::write(directory.path().join("Dockerfile"), "FROM {{ image }}\n{% if labels %}{% include \"partials/labels.txt\" %}{% endif %}\n").unwrap();
+        fs::write(directory.path().join("partials/labels.txt"), "LABEL service={{ services }}\n").unwrap();
+
+        let renderer = TemplateRenreder::from_delimiters(&DelimitersConfig::default())
+            .with_template_directory(&directory.path().to_path_buf());
+        let variables: Vec<String> = renderer.get_file_variables(&directory.path().join("Dockerfile"))
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(variables, vec!["image".to_string(), "labels".to_string(), "services".to_string()]);
+    }
+
+    #[test]
+    fn test_file_variables_detect_include_cycles() {
+        let directory = tempfile::tempdir().unwrap();
+        fs::write(directory.path().join("a.txt"), "{% include \"b.txt\" %}").unwrap();
+        fs::write(directory.path().join("b.txt"), "{{ name }}{% include \"a.txt\" %}").unwrap();
+
+        let renderer = TemplateRenreder::from_delimiters(&DelimitersConfig::default())
+            .with_template_directory(&directory.path().to_path_buf());
+        let message = renderer.get_file_variables(&directory.path().join("a.txt")).unwrap_err().to_string();
+        assert!(message.contains("include cycle detected: a.txt -> b.txt -> a.txt"), "{}", message);
+    }
+}
@@ -1,11 +1,11 @@
-use std::collections::HashMap;
-use std::fs::read_to_string;
-use std::path::PathBuf;
+use std::collections::{BTreeSet, HashMap};
+use std::fs::copy;
+use std::path::{Path, PathBuf};
 
-use fs_extra::copy_items;
-use fs_extra::dir::CopyOptions;
+use globset::GlobSet;
 use indicatif::{ProgressBar, ProgressStyle};
-use serde_json::Value as SerdeValue;
+use quick_error::ResultExt;
+use serde_json::{json, Value as SerdeValue};
 
 use crate::error::Error;
 use crate::constants::{
@@ -13,12 +13,15 @@ use crate::constants::{
     CREATING_DIRECTORIES_FOR_SOURCES_EMOJI, COPYING_FILES_TO_TARGET_EMOJI,
     GENERATING_FILES_FROM_TEMPLATES_EMOJI, INSTALLATION_TASK_HAS_FINISHED_EMOJI,
 };
-use crate::filesystem::{create_directory, get_directory_objects};
-use crate::templates::config::Config;
-use crate::templates::TemplateRenreder;
-use crate::templates::utils::{
-    generate_subcontexts, get_template_variables, merge_contexts
+use crate::filesystem::{
+    create_directory, get_directory_objects, get_ignore_patterns, is_binary_file,
+    CONFIG_NAMES, IGNORE_FILE_NAME,
 };
+use crate::templates::config::{Config, TemplateEntry};
+use crate::templates::{TemplateEngine, TemplateRenreder, TeraEngine};
+use crate::templates::engine::TERA_ENGINE_NAME;
+use crate::templates::renderer::get_context_value;
+use crate::templates::utils::{generate_subcontexts, merge_contexts};
 
 enum InstallStage {
     Started,
@@ -29,8 +32,18 @@ enum InstallStage {
     Finished,
 }
 
+/// File or directory from config[files][sources] that must be copied into
+/// the target path.
+struct SourceFile {
+    source_path: PathBuf,
+    target_path: PathBuf,
+    context: Box<SerdeValue>,
+    is_directory: bool,
+    is_rendered: bool,
+}
+
 pub struct Task {
-    template_renderer: Box<TemplateRenreder>,
+    template_engine: Box<dyn TemplateEngine>,
     project_directory_path: PathBuf,
     template_directory_path: PathBuf,
     config: Box<Config>,
@@ -41,22 +54,42 @@ impl Task {
     pub fn new(
         project_directory_path: &PathBuf,
         template_directory_path: &PathBuf,
-        config: &Box<Config>
-    ) -> Self {
+        config: &Config
+    ) -> Result<Self, Error> {
         let style = ProgressStyle::default_bar()
             .template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.green}] {msg}")
             .progress_chars("#>-");
-        let progress_bar = ProgressBar::new(0)
+        let progress_bar = ProgressBar::new(5)
             .with_style(style);
-        progress_bar.set_message(&format!("Initializing..."));
-        progress_bar.set_length(5);
 
-        Task {
-            template_renderer: Box::new(TemplateRenreder::new()),
+        Ok(Task {
+            template_engine: Task::get_engine_from_config(template_directory_path, config)?,
             project_directory_path: project_directory_path.to_owned(),
             template_directory_path: template_directory_path.to_owned(),
-            config: config.to_owned(),
+            config: Box::new(config.to_owned()),
             progress_bar: Box::new(progress_bar),
+        })
+    }
+
+    /// Creates the template engine, specified in the config.
+    fn get_engine_from_config(
+        template_directory_path: &PathBuf,
+        config: &Config,
+    ) -> Result<Box<dyn TemplateEngine>, Error> {
+        let templates = config.json_config.files.templates.clone().unwrap_or_default();
+        match config.get_engine() == TERA_ENGINE_NAME {
+            true => {
+                let tera_engine = TeraEngine::new(template_directory_path, &templates)?
+                    .with_generators(&config.generators);
+                Ok(Box::new(tera_engine))
+            },
+            false => {
+                let template_renderer = TemplateRenreder::from_delimiters(&config.get_delimiters())
+                    .with_template_directory(template_directory_path)
+                    .with_partials(&templates)
+                    .with_generators(&config.generators);
+                Ok(Box::new(template_renderer))
+            }
         }
     }
 
@@ -66,7 +99,7 @@ impl Task {
         let context = self.config.get_template_context();
         self.create_template_directories(&context)?;
         self.create_target_directories()?;
-        self.copy_files()?;
+        self.copy_files(&context)?;
         self.create_files_from_templates(&context)?;
         self.refresh_progress_bar(InstallStage::Finished);
         Ok(())
@@ -122,32 +155,29 @@ impl Task {
     }
 
     /// Creates directories based on data specified in the config[files][directories] space.
-    fn create_template_directories(&self, context: &Box<SerdeValue>) -> Result<(), Error> {
+    fn create_template_directories(&self, context: &SerdeValue) -> Result<(), Error> {
         self.refresh_progress_bar(InstallStage::CreatingTemplateDirectories);
 
-        self.config.clone().json_config.files.directories.unwrap_or_default()
-            .iter()
-            .for_each(|directory| {
-                let path_variables = get_template_variables(&directory);
-                match path_variables.is_empty() {
-                    // Path is static
-                    true => {
-                        let subdirectory_path = self.project_directory_path.join(directory);
-                        create_directory(&subdirectory_path).unwrap();
-                    },
-                    // Path is dynamic. Therefore generate subcontexts and the create folders
-                    false =>
-                        generate_subcontexts(context, &path_variables)
-                            .iter()
-                            .for_each(|subcontext| {
-                                let template_path = self.template_renderer
-                                    .render_template(directory, &subcontext);
-                                let generated_path = PathBuf::from(template_path);
-                                let subdirectory_path = self.project_directory_path.join(generated_path);
-                                create_directory(&subdirectory_path).unwrap();
-                            })
+        for directory in self.config.clone().json_config.files.directories.unwrap_or_default().iter() {
+            let path_variables = self.template_engine.get_template_variables(directory);
+            match path_variables.is_empty() {
+                // Path is static
+                true => {
+                    let subdirectory_path = self.project_directory_path.join(directory);
+                    create_directory(&subdirectory_path)?;
+                },
+                // Path is dynamic. Therefore generate subcontexts and the create folders
+                false => {
+                    for subcontext in generate_subcontexts(context, &path_variables).iter() {
+                        let template_path = self.template_engine
+                            .render_template(directory, subcontext)?;
+                        let generated_path = PathBuf::from(template_path);
+                        let subdirectory_path = self.project_directory_path.join(generated_path);
+                        create_directory(&subdirectory_path)?;
+                    }
                 }
-            });
+            }
+        }
         Ok(())
     }
 
@@ -157,9 +187,9 @@ impl Task {
 
         self.config.get_source_entries(&self.template_directory_path)
             .iter()
-            .map(|entry| entry.get("to").unwrap())
-            .filter(|str_path| **str_path != String::from("."))
-            .map(|path| PathBuf::from(path))
+            .map(|entry| entry.get_to())
+            .filter(|str_path| *str_path != ".")
+            .map(PathBuf::from)
             .for_each(|path| {
                 let directory_path = self.project_directory_path.join(path);
                 create_directory(&directory_path).unwrap();
@@ -168,87 +198,389 @@ impl Task {
     }
 
     /// Copy files from config[files][sources] into the config[files][to] directory.
-    fn copy_files(&self) -> Result<(), Error> {
+    /// Files that match the `render` patterns are rendered with the context.
+    fn copy_files(&self, context: &SerdeValue) -> Result<(), Error> {
         self.refresh_progress_bar(InstallStage::CopyingFiles);
 
-        self.config.get_source_entries(&self.template_directory_path)
-            .iter()
-            .map(|entry| (entry.get("from").unwrap(), entry.get("to").unwrap()))
-            .map(|(from_path, to_path)| {
-                let updated_to_path = match to_path == "." {
-                    true => self.project_directory_path.clone(),
-                    false => self.project_directory_path.join(to_path),
-                };
-                (PathBuf::from(from_path), updated_to_path)
-            })
-            .for_each(|(from_path, to_path)| {
-                 let items_to_copy = get_directory_objects(&from_path);
-                 let mut options = CopyOptions::new();
-                 options.overwrite = true;
-                 copy_items(&items_to_copy, to_path, &options).unwrap();
-            });
+        for source_file in self.get_source_files(context)?.iter() {
+            let target_path = self.project_directory_path.join(&source_file.target_path);
+            // Parent directories can be skipped by the `include` patterns
+            if let (false, Some(parent_path)) = (source_file.is_directory, target_path.parent()) {
+                create_directory(&parent_path.to_path_buf())?;
+            }
+
+            match (source_file.is_directory, source_file.is_rendered) {
+                (true, _) => create_directory(&target_path)?,
+                (false, true) => {
+                    self.template_engine.generate_file_from_template(
+                        &source_file.context,
+                        &source_file.source_path,
+                        &target_path
+                    )?;
+                },
+                (false, false) => {
+                    copy(&source_file.source_path, &target_path).context(&source_file.source_path)?;
+                },
+            }
+        }
         Ok(())
     }
 
+    /// Collects files and directories from config[files][sources] with their
+    /// target paths, relative to the project directory. Placeholders in the
+    /// names of files and directories are rendered, so that a single source
+    /// path can produce multiple target paths, each with its own subcontext.
+    fn get_source_files(&self, context: &SerdeValue) -> Result<Vec<SourceFile>, Error> {
+        let ignore_patterns = get_ignore_patterns(&self.template_directory_path)?;
+        let mut source_files = Vec::new();
+        for entry in self.config.get_source_entries(&self.template_directory_path).iter() {
+            let from_path = PathBuf::from(entry.get_from());
+            let to_path = match entry.get_to() == "." {
+                true => PathBuf::new(),
+                false => PathBuf::from(entry.get_to()),
+            };
+            let render_patterns = entry.get_render_patterns()?;
+            let binary_patterns = entry.get_binary_patterns()?;
+            let include_patterns = entry.get_include_patterns()?;
+            let exclude_patterns = entry.get_exclude_patterns()?;
+
+            for source_path in get_directory_objects(&from_path) {
+                let relative_path = source_path.strip_prefix(&from_path).unwrap().to_path_buf();
+                let is_directory = source_path.is_dir();
+                let is_excluded = self.is_ignored_path(&source_path, &ignore_patterns) ||
+                    is_matched_path(&relative_path, &exclude_patterns) ||
+                    (!include_patterns.is_empty() && !include_patterns.is_match(&relative_path));
+                if is_excluded {
+                    continue
+                }
+
+                let is_rendered = !is_directory &&
+                    render_patterns.is_match(&relative_path) &&
+                    !binary_patterns.is_match(&relative_path) &&
+                    !is_binary_file(&source_path)?;
+                for (target_path, used_context) in self.render_source_path(&source_path, &relative_path, context)? {
+                    source_files.push(SourceFile {
+                        source_path: source_path.clone(),
+                        target_path: to_path.join(target_path),
+                        context: used_context,
+                        is_directory,
+                        is_rendered,
+                    });
+                }
+            }
+        }
+        Ok(source_files)
+    }
+
+    /// Checks whether the file or the directory must be skipped during the
+    /// copying. The configuration files and the `.quickprojignore` file in the
+    /// root of the template are always skipped.
+    fn is_ignored_path(&self, source_path: &Path, ignore_patterns: &GlobSet) -> bool {
+        let is_template_file = CONFIG_NAMES
+            .iter()
+            .chain(std::iter::once(&IGNORE_FILE_NAME))
+            .any(|file_name| *source_path == self.template_directory_path.join(file_name));
+        let relative_path = source_path
+            .strip_prefix(&self.template_directory_path)
+            .unwrap_or(source_path);
+        is_template_file || is_matched_path(relative_path, ignore_patterns)
+    }
+
+    /// Renders each component of the relative path with all subcontexts of the
+    /// used variables. Each rendered component must be a non-empty name without
+    /// path separators.
+    fn render_source_path(
+        &self,
+        source_path: &Path,
+        relative_path: &Path,
+        context: &SerdeValue,
+    ) -> Result<Vec<(PathBuf, Box<SerdeValue>)>, Error> {
+        let path_variables = self.template_engine.get_template_variables(&relative_path.display().to_string());
+        if path_variables.is_empty() {
+            return Ok(vec![(relative_path.to_owned(), Box::new(context.clone()))])
+        }
+
+        // Variables missing in the context are left as is in the rendered path
+        let mut subcontexts = generate_subcontexts(context, &path_variables);
+        if subcontexts.is_empty() {
+            subcontexts.push(json!({}));
+        }
+
+        let mut rendered_paths = Vec::new();
+        for subcontext in subcontexts {
+            let mut used_context = subcontext;
+            merge_contexts(&mut used_context, context, &path_variables);
+
+            let mut target_path = PathBuf::new();
+            for component in relative_path.iter() {
+                let component = component.to_string_lossy().to_string();
+                let rendered_component = self.template_engine.render_template(&component, &used_context)?;
+                let has_separator = rendered_component.contains('/') || rendered_component.contains('\\');
+                if rendered_component.trim().is_empty() || has_separator {
+                    let message = format!(
+                        "{}: The `{}` path component was rendered as `{}`. Rendered names of \
+                        files and directories can't be empty or contain path separators.",
+                        source_path.display(), component, rendered_component
+                    );
+                    return Err(Error::Other(message))
+                }
+                target_path.push(rendered_component);
+            }
+
+            rendered_paths.push((target_path, Box::new(used_context)));
+        }
+        Ok(rendered_paths)
+    }
+
+    /// Checks whether the template must be rendered. Templates with disabled
+    /// rendering and binary files are copied as is. Binary files are detected
+    /// by the content, unless the `binary` marker is specified explicitly.
+    fn is_rendered_template(&self, template_entry: &TemplateEntry, template_path: &Path) -> Result<bool, Error> {
+        if !template_entry.is_rendered() {
+            return Ok(false)
+        }
+
+        match template_entry.get_binary_marker() {
+            Some(is_binary) => Ok(!is_binary),
+            None => Ok(!is_binary_file(template_path)?),
+        }
+    }
+
     /// Creates files specified in config[files][generated] with the prepared context.
-    fn create_files_from_templates(&self, context: &Box<SerdeValue>) -> Result<(), Error> {
+    fn create_files_from_templates(&self, context: &SerdeValue) -> Result<(), Error> {
         self.refresh_progress_bar(InstallStage::GeneratingFilesFromTemplates);
 
+        for (template_name, template_entry) in self.config.clone().json_config.files.templates.unwrap_or_default().iter() {
+            let full_template_path = self.template_directory_path.join(PathBuf::from(template_entry.get_path()));
+
+            // Templates with disabled rendering and binary files are copied as is
+            if !self.is_rendered_template(template_entry, &full_template_path)? {
+                let templates = self.get_generated_files(template_name, &BTreeSet::new(), context)?;
+                for target_file_path in templates.keys() {
+                    copy(&full_template_path, target_file_path).context(target_file_path)?;
+                }
+                continue
+            }
+
+            // Get all template variables from it
+            let template_variables = self.template_engine.get_file_variables(&full_template_path)?;
+
+            // Generate all files with its own subcontext
+            let templates = self.get_generated_files(template_name, &template_variables, context)?;
+            for (target_file_path, subcontext) in templates.iter() {
+                println!();
+                self.template_engine.generate_file_from_template(
+                    subcontext,
+                    &full_template_path,
+                    target_file_path
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Generates all possible variants of paths in config[files][generated] for the
+    /// template with the certain subcontext.
+    fn get_generated_files(
+        &self,
+        template_name: &String,
+        template_variables: &BTreeSet<String>,
+        context: &SerdeValue,
+    ) -> Result<HashMap<PathBuf, Box<SerdeValue>>, Error> {
         let generated_files = self.config.clone().json_config.files.generated.unwrap_or_default();
-        self.config.clone().json_config.files.templates.unwrap_or_default()
-            .iter()
-            .for_each(|(template_name, template_path)| {
-                // Get all template variables from the passed template
-                let full_template_path = self.template_directory_path.join(PathBuf::from(template_path));
-                let template_data = read_to_string(full_template_path.clone()).unwrap();
-                let template_variables = get_template_variables(&template_data);
-
-                // Generate possible all variants of paths with the certain subcontext
-                let mut templates: HashMap<PathBuf, Box<SerdeValue>> = HashMap::new();
-                generated_files.clone()
+        let mut templates: HashMap<PathBuf, Box<SerdeValue>> = HashMap::new();
+        for path in generated_files.iter().filter(|path| path.ends_with(template_name)) {
+            // Let's start from the check for dynamic paths (if was specified)
+            let path_variables = self.template_engine.get_template_variables(path);
+            match path_variables.is_empty() {
+                // Path is static. Shared context for everything
+                true => {
+                    templates.insert(self.project_directory_path.join(path), Box::new(context.clone()));
+                },
+                // Path is dynamic. Therefore each path has its own unique subcontext
+                false => {
+                    for subcontext in generate_subcontexts(context, &path_variables).iter() {
+                        // Generate all unique paths
+                        let template_path = self.template_engine
+                            .render_template(path, subcontext)?;
+                        let generated_path = PathBuf::from(template_path);
+                        let target_file_path = self.project_directory_path.join(generated_path);
+
+                        // Then prepare a unique subcontext for each path
+                        let mut used_context = subcontext.clone();
+                        merge_contexts(&mut used_context, context, template_variables);
+                        templates.insert(target_file_path, Box::new(used_context));
+                    }
+                }
+            };
+        }
+        Ok(templates)
+    }
+
+    /// Checks whether the task must fail on unresolved placeholders.
+    pub fn is_strict(&self) -> bool {
+        self.config.is_strict()
+    }
+
+    /// Collects all placeholders that can't be resolved from the context in
+    /// config[files][directories], config[files][generated], template files and
+    /// rendered files from config[files][sources].
+    /// Each entry of the report contains the source, the line and the variable name.
+    pub fn find_unresolved_placeholders(&self) -> Result<Vec<String>, Error> {
+        let context = self.config.get_template_context();
+        let files_config = self.config.clone().json_config.files;
+        let mut report: BTreeSet<String> = BTreeSet::new();
+
+        let directories = files_config.directories.unwrap_or_default();
+        let generated_files = files_config.generated.unwrap_or_default();
+        for (section, paths) in [("directories", &directories), ("generated", &generated_files)] {
+            for path in paths.iter() {
+                self.template_engine.get_template_variables(path)
                     .iter()
-                    .filter(|path| path.ends_with(template_name))
-                    .for_each(|path| {
-                        // Let's start from the check for dynamic paths (if was specified)
-                        let path_variables = get_template_variables(&path);
-                        match path_variables.is_empty() {
-                            // Path is static. Shared context for everything
-                            true => {
-                                templates.insert(self.project_directory_path.join(path), context.clone());
-                            },
-                            // Path is dynamic. Therefore each path has its own unique subcontext
-                            false => {
-                                generate_subcontexts(context, &path_variables)
-                                    .iter()
-                                    // Generate all unique paths
-                                    .map(|subcontext| {
-                                        let template_path = self.template_renderer
-                                            .render_template(path, &subcontext);
-                                        let generated_path = PathBuf::from(template_path);
-                                        (self.project_directory_path.join(generated_path), subcontext)
-                                    })
-                                    // Then prepare a unique subcontext for each path
-                                    .for_each(|(template_path, partial_context)| {
-                                        let mut used_context = partial_context.clone();
-                                        merge_contexts(&mut used_context, context, &template_variables);
-                                        templates.insert(template_path.to_owned(), Box::new(used_context));
-                                    });
-                            }
-                        };
+                    .filter(|variable_name| get_context_value(&context, variable_name).is_none())
+                    .for_each(|variable_name| {
+                        report.insert(format!("files.{} `{}` -> `{}`", section, path, variable_name));
                     });
+            }
+        }
+
+        for (template_name, template_entry) in files_config.templates.unwrap_or_default().iter() {
+            let template_path = template_entry.get_path();
+            let full_template_path = self.template_directory_path.join(PathBuf::from(template_path));
+            if !self.is_rendered_template(template_entry, &full_template_path)? {
+                continue
+            }
+
+            let template_variables = self.template_engine.get_file_variables(&full_template_path)?;
 
-                // And then generate all files with its own subcontext
-                templates
+            let templates = self.get_generated_files(template_name, &template_variables, &context)?;
+            for subcontext in templates.values() {
+                let rendered_template = self.template_engine.render_file(&full_template_path, subcontext)?;
+                rendered_template.unresolved
                     .iter()
-                    .for_each(|(target_file_path, subcontext)| {
-                        println!("");
-                        self.template_renderer.generate_file_from_template(
-                            subcontext,
-                            &full_template_path,
-                            target_file_path
-                        ).unwrap();
+                    .for_each(|placeholder| {
+                        report.insert(format!(
+                            "{}:{} -> `{}`",
+                            placeholder.file.as_ref().unwrap_or(template_path),
+                            placeholder.line,
+                            placeholder.name
+                        ));
                     });
-            });
-        Ok(())
+            }
+        }
+
+        for source_file in self.get_source_files(&context)? {
+            let source_path = source_file.source_path
+                .strip_prefix(&self.template_directory_path)
+                .unwrap_or(&source_file.source_path)
+                .display()
+                .to_string();
+            self.template_engine.get_template_variables(&source_path)
+                .iter()
+                .filter(|variable_name| get_context_value(&context, variable_name).is_none())
+                .for_each(|variable_name| {
+                    report.insert(format!("files.sources `{}` -> `{}`", source_path, variable_name));
+                });
+
+            if !source_file.is_rendered || source_file.is_directory {
+                continue
+            }
+
+            let rendered_template = self.template_engine.render_file(&source_file.source_path, &source_file.context)?;
+            rendered_template.unresolved
+                .iter()
+                .for_each(|placeholder| {
+                    report.insert(format!(
+                        "{}:{} -> `{}`",
+                        placeholder.file.as_ref().unwrap_or(&source_path),
+                        placeholder.line,
+                        placeholder.name
+                    ));
+                });
+        }
+
+        Ok(report.into_iter().collect())
+    }
+}
+
+/// Checks whether the path or any of its parent directories matches the patterns.
+fn is_matched_path(relative_path: &Path, patterns: &GlobSet) -> bool {
+    relative_path
+        .ancestors()
+        .filter(|path| !path.as_os_str().is_empty())
+        .any(|path| patterns.is_match(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::templates::config::JsonConfig;
+
+    fn get_task(template_directory: &Path) -> Task {
+        let mut json_config: JsonConfig = serde_json::from_value(json!({"files": {"sources": []}})).unwrap();
+        json_config.init_missing_fields();
+        let config = Config::new(json_config);
+        Task::new(&PathBuf::from("app"), &template_directory.to_path_buf(), &config).unwrap()
+    }
+
+    #[test]
+    fn test_template_files_are_always_ignored() {
+        let directory = tempfile::tempdir().unwrap();
+        fs::write(directory.path().join(IGNORE_FILE_NAME), "/dist\n").unwrap();
+        let task = get_task(directory.path());
+        let ignore_patterns = get_ignore_patterns(directory.path()).unwrap();
+        let is_ignored = |path: &str| task.is_ignored_path(&directory.path().join(path), &ignore_patterns);
+
+        for file_name in CONFIG_NAMES.iter().chain(std::iter::once(&IGNORE_FILE_NAME)) {
+            assert!(is_ignored(file_name), "`{}` must be ignored", file_name);
+        }
+        // Files with the same names in nested directories are copied
+        assert!(!is_ignored("src/config.json"));
+        assert!(!is_ignored("src/.quickprojignore"));
+        assert!(is_ignored("dist/app.js"));
+        assert!(!is_ignored("src/dist/app.js"));
+    }
+
+    #[test]
+    fn test_source_paths_are_rendered() {
+        let directory = tempfile::tempdir().unwrap();
+        let task = get_task(directory.path());
+        let context = json!({"name": "app", "envs": ["dev", "prod"]});
+        let render = |path: &str| -> Vec<String> {
+            task.render_source_path(Path::new(path), Path::new(path), &context)
+                .unwrap()
+                .into_iter()
+                .map(|(target_path, _)| target_path.display().to_string())
+                .collect()
+        };
+
+        assert_eq!(render("src/main.rs"), vec!["src/main.rs"]);
+        assert_eq!(render("src/{{ name }}/lib.rs"), vec!["src/app/lib.rs"]);
+        // Each item of the list produces its own path
+        assert_eq!(render("deploy/{{ envs }}.yaml"), vec!["deploy/dev.yaml", "deploy/prod.yaml"]);
+        // Unknown variables are left as is
+        assert_eq!(render("{{ missing }}.txt"), vec!["{{ missing }}.txt"]);
+    }
+
+    #[test]
+    fn test_invalid_rendered_path_components_are_errors() {
+        let directory = tempfile::tempdir().unwrap();
+        let task = get_task(directory.path());
+        let context = json!({"empty": "", "nested": "a/b"});
+        let get_error = |path: &str| -> String {
+            match task.render_source_path(Path::new(path), Path::new(path), &context) {
+                Err(err) => err.to_string(),
+                Ok(_) => panic!("expected the invalid path `{}`", path),
+            }
+        };
+
+        assert_eq!(
+            get_error("src/{{ empty }}/lib.rs"),
+            "src/{{ empty }}/lib.rs: The `{{ empty }}` path component was rendered as ``. Rendered names of \
+            files and directories can't be empty or contain path separators."
+        );
+        assert!(get_error("{{ nested }}.rs").contains("was rendered as `a/b.rs`"));
     }
 }
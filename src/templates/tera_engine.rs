@@ -0,0 +1,201 @@
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error as StdError;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use heck::{CamelCase, KebabCase, MixedCase, ShoutySnakeCase, SnakeCase};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde_json::Value as SerdeValue;
+use tera::{Context, Tera, Value as TeraValue};
+
+use crate::error::Error;
+use crate::filesystem::{is_binary_file, read_text_file};
+use crate::templates::config::TemplateEntry;
+use crate::templates::engine::TemplateEngine;
+use crate::templates::generators::{FunctionCall, Generators, is_function_call};
+use crate::templates::renderer::RenderedTemplate;
+use crate::templates::utils::get_template_variables;
+
+lazy_static! {
+    static ref TERA_VARIABLE_REGEX: Regex = Regex::new(r"\{\{-?\s*(?P<name>.+?)\s*-?}}").unwrap();
+}
+
+/// Template engine with the Jinja2-like syntax, provided by the Tera library.
+/// Supports expressions, macros, template inheritance and the built-in Tera
+/// filters, extended with the case conversion filters of the default engine.
+pub struct TeraEngine {
+    tera: RefCell<Tera>,
+    template_directory_path: PathBuf,
+}
+
+impl TeraEngine {
+    /// Creates the engine with the templates from the config[files][templates]
+    /// space, so that they can be included, extended or imported by the key or
+    /// by the path, relative to the template directory.
+    pub fn new(template_directory_path: &PathBuf, templates: &HashMap<String, TemplateEntry>) -> Result<Self, Error> {
+        let mut tera = Tera::default();
+        tera.autoescape_on(Vec::new());
+        register_case_filters(&mut tera);
+
+        let mut partials: Vec<(String, String)> = Vec::new();
+        for (template_name, template_entry) in templates.iter() {
+            let template_path = template_directory_path.join(template_entry.get_path());
+            let is_binary = match template_entry.get_binary_marker() {
+                Some(is_binary) => is_binary,
+                None => is_binary_file(&template_path)?,
+            };
+            if !template_entry.is_rendered() || is_binary {
+                continue
+            }
+
+            let data = read_text_file(&template_path)?;
+            partials.push((template_name.to_owned(), data.clone()));
+            partials.push((template_entry.get_path().to_owned(), data));
+        }
+
+        tera.add_raw_templates(partials).map_err(|err| {
+            let message = format!("{}: {}", template_directory_path.display(), get_error_message(&err));
+            Error::Other(message)
+        })?;
+
+        Ok(TeraEngine {
+            tera: RefCell::new(tera),
+            template_directory_path: template_directory_path.to_owned(),
+        })
+    }
+
+    /// Registers the generator functions, backed by the shared generators, so
+    /// that function calls return the same values in all used templates.
+    pub fn with_generators(mut self, generators: &Arc<Generators>) -> Self {
+        register_generator_functions(self.tera.get_mut(), generators);
+        self
+    }
+
+    /// Returns the name of the template file, registered in Tera. Files that
+    /// aren't declared in the config[files][templates] space, e.g. rendered
+    /// sources, are registered on the first use.
+    fn load_template(&self, template_path: &Path) -> Result<String, Error> {
+        let template_name = template_path
+            .strip_prefix(&self.template_directory_path)
+            .unwrap_or(template_path)
+            .display()
+            .to_string();
+
+        if !self.tera.borrow().get_template_names().any(|name| name == template_name) {
+            let data = read_text_file(template_path)?;
+            self.tera.borrow_mut().add_raw_template(&template_name, &data).map_err(|err| {
+                let message = format!("{}: {}", template_path.display(), get_error_message(&err));
+                Error::Other(message)
+            })?;
+        }
+
+        Ok(template_name)
+    }
+}
+
+impl TemplateEngine for TeraEngine {
+    fn get_template_variables(&self, data: &str) -> BTreeSet<String> {
+        get_template_variables(data, &TERA_VARIABLE_REGEX)
+            .into_iter()
+            .filter(|variable_name| !is_function_call(variable_name))
+            .collect()
+    }
+
+    fn get_file_variables(&self, template_path: &Path) -> Result<BTreeSet<String>, Error> {
+        let data = read_text_file(template_path)?;
+        Ok(self.get_template_variables(&data))
+    }
+
+    /// Tera fails on undefined variables, so that the rendered template never
+    /// contains unresolved placeholders.
+    fn render_file(&self, template_path: &Path, context: &SerdeValue) -> Result<RenderedTemplate, Error> {
+        let template_name = self.load_template(template_path)?;
+        let data = Context::from_value(context.clone())
+            .and_then(|context| self.tera.borrow().render(&template_name, &context))
+            .map_err(|err| {
+                let message = format!("{}: {}", template_path.display(), get_error_message(&err));
+                Error::Other(message)
+            })?;
+
+        Ok(RenderedTemplate { data, unresolved: Vec::new() })
+    }
+
+    fn render_template(&self, data: &str, context: &SerdeValue) -> Result<String, Error> {
+        Context::from_value(context.clone())
+            .and_then(|context| self.tera.borrow_mut().render_str(data, &context))
+            .map_err(|err| {
+                let message = format!("`{}`: {}", data, get_error_message(&err));
+                Error::Other(message)
+            })
+    }
+}
+
+/// Registers the case conversion filters, which aren't available in Tera.
+fn register_case_filters(tera: &mut Tera) {
+    let filter_names = ["snake_case", "kebab_case", "pascal_case", "camel_case", "shouty_snake_case"];
+    for filter_name in filter_names.iter().cloned() {
+        tera.register_filter(filter_name, move |value: &TeraValue, _args: &HashMap<String, TeraValue>| {
+            match value {
+                TeraValue::String(data) => Ok(TeraValue::String(convert_case(filter_name, data))),
+                _ => Err(tera::Error::msg(format!("the `{}` filter can be applied only to strings", filter_name))),
+            }
+        });
+    }
+}
+
+/// Registers the generator functions with the named arguments, e.g.
+/// `random_string(length=32)` or `random_port(min=8000, max=9000)`.
+fn register_generator_functions(tera: &mut Tera, generators: &Arc<Generators>) {
+    let functions: [(&str, &[&str]); 3] = [
+        ("uuid", &[]),
+        ("random_string", &["length"]),
+        ("random_port", &["min", "max"]),
+    ];
+    for (function_name, arg_names) in functions.iter().cloned() {
+        let generators = generators.clone();
+        tera.register_function(function_name, move |args: &HashMap<String, TeraValue>| {
+            if let Some(arg_name) = args.keys().find(|arg_name| !arg_names.contains(&arg_name.as_str())) {
+                let message = format!("the `{}` function doesn't accept the `{}` argument", function_name, arg_name);
+                return Err(tera::Error::msg(message))
+            }
+
+            let args = arg_names
+                .iter()
+                .filter_map(|arg_name| args.get(*arg_name))
+                .map(|value| match value {
+                    TeraValue::String(value) => value.to_owned(),
+                    value => value.to_string(),
+                })
+                .collect();
+            FunctionCall::new(function_name, args)
+                .and_then(|function_call| generators.get_value(&function_call))
+                .map(TeraValue::String)
+                .map_err(|err| tera::Error::msg(err.to_string()))
+        });
+    }
+}
+
+fn convert_case(filter_name: &str, data: &str) -> String {
+    match filter_name {
+        "snake_case" => data.to_snake_case(),
+        "kebab_case" => data.to_kebab_case(),
+        "pascal_case" => data.to_camel_case(),
+        "camel_case" => data.to_mixed_case(),
+        "shouty_snake_case" => data.to_shouty_snake_case(),
+        _ => unreachable!(),
+    }
+}
+
+/// Joins the Tera error with its causes, because the top-level error only
+/// mentions the failed template.
+fn get_error_message(err: &tera::Error) -> String {
+    let mut messages = vec![err.to_string()];
+    let mut source = err.source();
+    while let Some(cause) = source {
+        messages.push(cause.to_string());
+        source = cause.source();
+    }
+    messages.join(": ")
+}
@@ -1,13 +1,15 @@
 use std::collections::{HashMap, BTreeSet};
 
+use regex::Regex;
 use serde_json::{json, Value as SerdeValue};
 
 use crate::error::Error;
-use crate::templates::TEMPLATE_VARIABLE_REGEX;
+use crate::templates::filters::split_outside_quotes;
+use crate::templates::renderer::get_context_value;
 
 /// Checks that the specified templates are available to use.
 pub fn is_correct_template_list(
-    templates: &Vec<String>,
+    templates: &[String],
     defined_templates: &HashMap<String, String>,
 ) -> Result<(), Error> {
     if templates.is_empty() {
@@ -16,29 +18,30 @@ pub fn is_correct_template_list(
     }
 
     let invalid_template_names: Vec<String> = templates
-        .into_iter()
-        .filter(|name| !defined_templates.contains_key(name.clone()))
-        .map(|name| name.clone())
+        .iter()
+        .filter(|name| !defined_templates.contains_key(*name))
+        .cloned()
         .collect();
 
     match invalid_template_names.is_empty() {
         true => Ok(()),
         false => {
             let values = invalid_template_names.join(", ");
-            let message = String::from(format!(
+            let message = format!(
                 "The templates with the following names weren't found or not available: {}",
                 values
-            ));
+            );
             Err(Error::Other(message))
         }
     }
 }
 
-/// Extract all template variables in according to the TEMPLATE_VARIABLE_REGEX regex.
-pub fn get_template_variables(data: &String) -> BTreeSet<String> {
+/// Extract all template variables in according to the variable regex of the renderer.
+/// Filters applied to the variables are not the part of the variable name.
+pub fn get_template_variables(data: &str, variable_regex: &Regex) -> BTreeSet<String> {
     let mut used_variables = BTreeSet::new();
-    for capture in TEMPLATE_VARIABLE_REGEX.captures_iter(data) {
-        let value: String = capture["name"].trim().to_string();
+    for capture in variable_regex.captures_iter(data) {
+        let value: String = split_outside_quotes(&capture["name"], '|')[0].trim().to_string();
         used_variables.insert(value);
     }
     used_variables
@@ -46,20 +49,34 @@ pub fn get_template_variables(data: &String) -> BTreeSet<String> {
 
 
 /// Merges two template contexts together with filtering by keys.
+/// Nested keys are filtered by the dotted names, like `env.USER`.
 pub fn merge_contexts(
     a: &mut SerdeValue,
     b: &SerdeValue,
     keys: &BTreeSet<String>,
+) {
+    merge_nested_contexts(a, b, keys, "")
+}
+
+fn merge_nested_contexts(
+    a: &mut SerdeValue,
+    b: &SerdeValue,
+    keys: &BTreeSet<String>,
+    prefix: &str,
 ) {
     match (a, b) {
-        (&mut SerdeValue::Object(ref mut a), &SerdeValue::Object(ref b)) => {
+        (&mut SerdeValue::Object(ref mut a), SerdeValue::Object(b)) => {
             for (key, value) in b {
-                let string_key = key.to_string();
-                if keys.contains(&string_key) && a.contains_key(&string_key) {
+                let full_key = match prefix.is_empty() {
+                    true => key.to_string(),
+                    false => format!("{}.{}", prefix, key),
+                };
+                if keys.contains(&full_key) && a.contains_key(key) {
                     continue
                 }
 
-                merge_contexts(a.entry(key.clone()).or_insert(SerdeValue::Null), value, keys)
+                let entry = a.entry(key.clone()).or_insert(SerdeValue::Null);
+                merge_nested_contexts(entry, value, keys, &full_key)
             }
         },
         (a, b) => {
@@ -68,42 +85,71 @@ pub fn merge_contexts(
     }
 }
 
+/// Sets the value in the context. Dotted names that aren't the keys of the
+/// source context, like `env.USER`, are stored as the nested objects.
+fn set_context_value(
+    subcontext: &mut SerdeValue,
+    context: &SerdeValue,
+    name: &str,
+    value: SerdeValue,
+) {
+    let keys: Vec<&str> = match context.get(name) {
+        Some(_) => vec![name],
+        None => name.split('.').collect(),
+    };
+
+    let mut current = subcontext;
+    for key in &keys[..keys.len() - 1] {
+        if !current.get(*key).is_some_and(SerdeValue::is_object) {
+            current[*key] = json!({});
+        }
+        current = current.get_mut(*key).unwrap();
+    }
+    current[keys[keys.len() - 1]] = value;
+}
+
 /// Generate all possible combinations of subcontexts that will be used for the template.
 pub fn generate_subcontexts(
-    context: &Box<SerdeValue>,
+    context: &SerdeValue,
     variables: &BTreeSet<String>
 ) -> Vec<SerdeValue> {
     let mut data = variables
         .iter()
         .filter(|variable_name| {
-            let value = match context.get(*variable_name) {
+            let value = match get_context_value(context, variable_name) {
                 Some(value) => value,
                 None => return false,
             };
 
-            match value {
-                SerdeValue::String(_) => true,
-                SerdeValue::Array(_) => true,
-                _ => false,
-            }
+            matches!(
+                value,
+                SerdeValue::String(_) | SerdeValue::Array(_) | SerdeValue::Bool(_) | SerdeValue::Number(_)
+            )
         })
         .map(|variable_name| {
             let mut entry: HashMap<String, SerdeValue> = HashMap::new();
-            entry.insert(variable_name.clone(), context.get(variable_name).unwrap().clone());
+            entry.insert(variable_name.clone(), get_context_value(context, variable_name).unwrap().clone());
             entry
         })
         .collect();
 
     get_combinations(&mut data)
-        .iter()
-        .map(|combination| json!(combination))
+        .into_iter()
+        .map(|combination| {
+            let mut subcontext = json!({});
+            for (name, value) in combination {
+                set_context_value(&mut subcontext, context, &name, SerdeValue::String(value));
+            }
+            subcontext
+        })
         .collect()
 }
 
 /// Generate all possible combinations for the given context without repeats.
 ///
 /// Currently getting combinations is supported for hashmaps where values
-/// represented as the String or as the Vec<String> types.
+/// represented as the String or as the Vec<String> types. Booleans and numbers
+/// are converted into strings.
 fn get_combinations(
     data: &mut Vec<HashMap<String, SerdeValue>>
 ) -> Vec<HashMap<String, String>> {
@@ -112,8 +158,7 @@ fn get_combinations(
     }
 
     let mut combinations: Vec<HashMap<String, String>> = Vec::new();
-    while !data.is_empty() {
-        let variable_data = data.pop().unwrap();
+    while let Some(variable_data) = data.pop() {
         let mut pairs: Vec<HashMap<String, String>> = Vec::new();
         for (key, value) in variable_data {
             match value {
@@ -121,7 +166,6 @@ fn get_combinations(
                     let mut hashmap = HashMap::new();
                     hashmap.insert(key.clone(), value.clone());
                     pairs.push(hashmap);
-                    ()
                 },
                 SerdeValue::Array(values) => {
                     for value in values {
@@ -134,7 +178,11 @@ fn get_combinations(
                         hashmap.insert(key.clone(), stringified_value);
                         pairs.push(hashmap);
                     }
-                    ()
+                },
+                SerdeValue::Bool(_) | SerdeValue::Number(_) => {
+                    let mut hashmap = HashMap::new();
+                    hashmap.insert(key.clone(), value.to_string());
+                    pairs.push(hashmap);
                 },
                 _ => ()
             }
@@ -167,3 +215,53 @@ fn cartesian_hashmap_product(
     }
     cartesian_product
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use serde_json::json;
+
+    use super::{generate_subcontexts, merge_contexts};
+
+    fn get_variables(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_subcontexts_resolve_dotted_variables() {
+        let context = json!({"env": {"USER": "bob", "HOME": "/home/bob"}, "envs": ["dev", "prod"]});
+        let mut subcontexts = generate_subcontexts(&context, &get_variables(&["env.USER", "envs"]));
+        subcontexts.sort_by_key(|subcontext| subcontext["envs"].to_string());
+
+        assert_eq!(subcontexts, vec![
+            json!({"env": {"USER": "bob"}, "envs": "dev"}),
+            json!({"env": {"USER": "bob"}, "envs": "prod"}),
+        ]);
+    }
+
+    #[test]
+    fn test_subcontexts_keep_dotted_keys() {
+        let context = json!({"app.name": "demo"});
+        let subcontexts = generate_subcontexts(&context, &get_variables(&["app.name"]));
+
+        assert_eq!(subcontexts, vec![json!({"app.name": "demo"})]);
+    }
+
+    #[test]
+    fn test_subcontexts_skip_missing_variables() {
+        let context = json!({"env": {}});
+        let subcontexts = generate_subcontexts(&context, &get_variables(&["env.USER"]));
+
+        assert!(subcontexts.is_empty());
+    }
+
+    #[test]
+    fn test_merge_keeps_nested_subcontext_values() {
+        let context = json!({"app": {"envs": ["dev", "prod"], "name": "demo"}});
+        let mut subcontext = json!({"app": {"envs": "dev"}});
+        merge_contexts(&mut subcontext, &context, &get_variables(&["app.envs"]));
+
+        assert_eq!(subcontext, json!({"app": {"envs": "dev", "name": "demo"}}));
+    }
+}
@@ -0,0 +1,445 @@
+use std::fmt;
+
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{json, Value as SerdeValue};
+
+use crate::templates::conditions::Condition;
+
+/// Type of the variable in the config[variables] space.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VariableType {
+    Bool,
+    Int,
+    Choice,
+    String,
+    List,
+}
+
+/// Detailed declaration of the variable in the config[variables] space. The
+/// shorthand form (`"name": "value"` or `"name": ["a", "b"]`) is treated as
+/// the string or the list variable with the given default value.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VariableOptions {
+    #[serde(rename = "type")]
+    pub variable_type: Option<VariableType>,
+    pub default: Option<SerdeValue>,
+    pub choices: Option<Vec<String>>,
+    pub required: Option<bool>,
+    pub description: Option<String>,
+    pub prompt: Option<String>,
+    pub help: Option<String>,
+    pub examples: Option<Vec<String>>,
+    pub pattern: Option<String>,
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    pub min_items: Option<usize>,
+    pub max_items: Option<usize>,
+    pub when: Option<String>,
+}
+
+impl VariableOptions {
+    /// Parses the declaration of the variable and checks that the default
+    /// value and the choices match the variable type.
+    pub fn from_declaration(declaration: &SerdeValue) -> Result<VariableOptions, String> {
+        let mut options = match declaration {
+            SerdeValue::Object(_) => {
+                serde_json::from_value::<VariableOptions>(declaration.clone())
+                    .map_err(|err| err.to_string())?
+            },
+            // The shorthand form keeps the value as is
+            SerdeValue::String(_) | SerdeValue::Array(_) => {
+                return Ok(VariableOptions {
+                    default: Some(declaration.clone()),
+                    ..VariableOptions::default()
+                })
+            },
+            _ => return Err(String::from("unsupported value type")),
+        };
+        options.variable_type = Some(options.get_type());
+
+        let variable_type = options.get_type();
+        let choices = options.choices.clone().unwrap_or_default();
+        match variable_type {
+            VariableType::Choice if choices.is_empty() => {
+                return Err(String::from("the `choice` type requires a non-empty `choices` list"))
+            },
+            VariableType::Choice | VariableType::List => {},
+            _ if options.choices.is_some() => {
+                let message = format!("the `choices` key isn't supported by the `{}` type", variable_type);
+                return Err(message)
+            },
+            _ => {},
+        }
+
+        options.validate_constraints()?;
+
+        if let Some(when) = &options.when {
+            Condition::parse(when)
+                .map_err(|err| format!("invalid `when` condition `{}`: {}", when, err))?;
+        }
+
+        if let Some(default_value) = &options.default {
+            options.validate_value(default_value)
+                .map_err(|err| format!("invalid default value: {}", err))?;
+        }
+
+        Ok(options)
+    }
+
+    /// Checks that the constraints are applicable to the variable type and
+    /// that the pattern is a valid regular expression.
+    fn validate_constraints(&self) -> Result<(), String> {
+        let variable_type = self.get_type();
+        let has_text_constraints = self.pattern.is_some() || self.min_length.is_some() || self.max_length.is_some();
+        let has_list_constraints = self.min_items.is_some() || self.max_items.is_some();
+        match variable_type {
+            VariableType::Bool | VariableType::Int if has_text_constraints => {
+                let message = format!(
+                    "the `pattern`, `min_length` and `max_length` keys aren't supported by the `{}` type",
+                    variable_type
+                );
+                return Err(message)
+            },
+            VariableType::List => {},
+            _ if has_list_constraints => {
+                let message = format!(
+                    "the `min_items` and `max_items` keys aren't supported by the `{}` type",
+                    variable_type
+                );
+                return Err(message)
+            },
+            _ => {},
+        }
+
+        for (name, min_value, max_value) in [
+            ("length", self.min_length, self.max_length),
+            ("items", self.min_items, self.max_items),
+        ] {
+            if let (Some(min_value), Some(max_value)) = (min_value, max_value) {
+                if min_value > max_value {
+                    let message = format!(
+                        "the `min_{}` value ({}) is greater than the `max_{}` value ({})",
+                        name, min_value, name, max_value
+                    );
+                    return Err(message)
+                }
+            }
+        }
+
+        if let Some(pattern) = &self.pattern {
+            get_pattern_regex(pattern)
+                .map_err(|err| format!("the `{}` pattern is invalid: {}", pattern, err))?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the declared type or infers it from the choices and the default value.
+    pub fn get_type(&self) -> VariableType {
+        if let Some(variable_type) = self.variable_type {
+            return variable_type
+        }
+
+        match (&self.choices, &self.default) {
+            (Some(_), _) => VariableType::Choice,
+            (None, Some(SerdeValue::Bool(_))) => VariableType::Bool,
+            (None, Some(SerdeValue::Number(_))) => VariableType::Int,
+            (None, Some(SerdeValue::Array(_))) => VariableType::List,
+            _ => VariableType::String,
+        }
+    }
+
+    pub fn is_required(&self) -> bool {
+        self.required.unwrap_or(false)
+    }
+
+    /// Returns the parsed `when` condition of the variable.
+    pub fn get_condition(&self) -> Option<Condition> {
+        self.when.as_ref().and_then(|when| Condition::parse(when).ok())
+    }
+
+    /// Checks whether the variable is relevant for the context, built from
+    /// the built-in variables and the variables declared before it.
+    pub fn is_enabled(&self, context: &SerdeValue) -> bool {
+        match self.get_condition() {
+            Some(condition) => condition.evaluate(context),
+            None => true,
+        }
+    }
+
+    /// Returns the value of the variable. Optional variables without the
+    /// default value fall back to the empty value of the type, or to the
+    /// first choice. Required variables must have a non-empty value.
+    pub fn get_value(&self) -> Option<SerdeValue> {
+        let value = match &self.default {
+            Some(value) => value.clone(),
+            None => match self.get_type() {
+                VariableType::Bool => json!(false),
+                VariableType::Int => json!(0),
+                VariableType::Choice => json!(self.choices.clone().unwrap_or_default().first()),
+                VariableType::String => json!(""),
+                VariableType::List => json!([]),
+            },
+        };
+
+        let is_empty = match &value {
+            SerdeValue::String(data) => data.is_empty(),
+            SerdeValue::Array(values) => values.is_empty(),
+            _ => false,
+        };
+        match self.is_required() && (self.default.is_none() || is_empty) {
+            true => None,
+            false => Some(value),
+        }
+    }
+
+    /// Checks that the value matches the variable type and the choices.
+    pub fn validate_value(&self, value: &SerdeValue) -> Result<(), String> {
+        let variable_type = self.get_type();
+        let is_valid_type = match variable_type {
+            VariableType::Bool => value.is_boolean(),
+            VariableType::Int => value.is_i64(),
+            VariableType::Choice | VariableType::String => value.is_string(),
+            VariableType::List => value.as_array().map(|values| values.iter().all(|item| item.is_string())).unwrap_or(false),
+        };
+        if !is_valid_type {
+            return Err(format!("`{}` isn't a value of the `{}` type", value, variable_type))
+        }
+
+        let items = match value {
+            SerdeValue::Array(values) => values.clone(),
+            _ => vec![value.clone()],
+        };
+        for item in items.iter().filter_map(|item| item.as_str()) {
+            if let Some(choices) = &self.choices {
+                if !choices.iter().any(|choice| choice == item) {
+                    return Err(format!("`{}` isn't one of the choices: {}", item, choices.join(", ")))
+                }
+            }
+            self.validate_text(item)?;
+        }
+
+        if let SerdeValue::Array(values) = value {
+            if self.min_items.map(|min_items| values.len() < min_items).unwrap_or(false) {
+                return Err(format!("the list must contain at least {} item(s)", self.min_items.unwrap()))
+            }
+            if self.max_items.map(|max_items| values.len() > max_items).unwrap_or(false) {
+                return Err(format!("the list must contain at most {} item(s)", self.max_items.unwrap()))
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks the string value (or the item of the list) against the pattern
+    /// and the length constraints.
+    fn validate_text(&self, value: &str) -> Result<(), String> {
+        let length = value.chars().count();
+        if self.min_length.map(|min_length| length < min_length).unwrap_or(false) {
+            return Err(format!("`{}` must be at least {} character(s) long", value, self.min_length.unwrap()))
+        }
+        if self.max_length.map(|max_length| length > max_length).unwrap_or(false) {
+            return Err(format!("`{}` must be at most {} character(s) long", value, self.max_length.unwrap()))
+        }
+
+        if let Some(pattern) = &self.pattern {
+            let is_match = get_pattern_regex(pattern)
+                .map(|regex| regex.is_match(value))
+                .unwrap_or(false);
+            if !is_match {
+                return Err(format!("`{}` doesn't match the `{}` pattern", value, pattern))
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Converts the user input into the value of the variable type. Returns
+    /// `None` for the empty input, so that the current value is used.
+    pub fn parse_input(&self, raw_value: &str) -> Result<Option<SerdeValue>, String> {
+        let raw_value = raw_value.trim();
+        if raw_value.is_empty() {
+            return match self.get_value() {
+                Some(_) => Ok(None),
+                None => Err(String::from("The value is required.")),
+            }
+        }
+
+        let value = match self.get_type() {
+            VariableType::Bool => match raw_value.to_lowercase().as_str() {
+                "y" | "yes" | "true" | "on" | "1" => json!(true),
+                "n" | "no" | "false" | "off" | "0" => json!(false),
+                _ => return Err(String::from("Expected `yes` or `no`.")),
+            },
+            VariableType::Int => {
+                let number = raw_value
+                    .parse::<i64>()
+                    .map_err(|_| String::from("Expected an integer number."))?;
+                json!(number)
+            },
+            VariableType::Choice => {
+                let choices = self.choices.clone().unwrap_or_default();
+                let choice = raw_value
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| index.checked_sub(1))
+                    .and_then(|index| choices.get(index).cloned())
+                    .unwrap_or(raw_value.to_string());
+                json!(choice)
+            },
+            VariableType::String => json!(raw_value),
+            VariableType::List => {
+                let items: Vec<String> = raw_value
+                    .split(',')
+                    .map(|item| item.trim().to_string())
+                    .filter(|item| !item.is_empty())
+                    .collect();
+                if items.is_empty() {
+                    return Err(String::from("Expected a comma separated list of values."))
+                }
+                json!(items)
+            },
+        };
+
+        self.validate_value(&value).map_err(|err| format!("Invalid value: {}.", err))?;
+        Ok(Some(value))
+    }
+}
+
+/// Compiles the pattern, which must match the whole value.
+fn get_pattern_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    Regex::new(&format!("^(?:{})$", pattern))
+}
+
+impl fmt::Display for VariableType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            VariableType::Bool => "bool",
+            VariableType::Int => "int",
+            VariableType::Choice => "choice",
+            VariableType::String => "string",
+            VariableType::List => "list",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn declare(declaration: SerdeValue) -> VariableOptions {
+        VariableOptions::from_declaration(&declaration).unwrap()
+    }
+
+    fn get_error(declaration: SerdeValue) -> String {
+        VariableOptions::from_declaration(&declaration).unwrap_err()
+    }
+
+    #[test]
+    fn test_variable_types_are_inferred() {
+        assert_eq!(declare(json!("app")).get_type(), VariableType::String);
+        assert_eq!(declare(json!(["a", "b"])).get_type(), VariableType::List);
+        assert_eq!(declare(json!({"default": true})).get_type(), VariableType::Bool);
+        assert_eq!(declare(json!({"default": 8080})).get_type(), VariableType::Int);
+        assert_eq!(declare(json!({"choices": ["a", "b"]})).get_type(), VariableType::Choice);
+        assert_eq!(declare(json!({"type": "list", "default": []})).get_type(), VariableType::List);
+    }
+
+    #[test]
+    fn test_invalid_declarations() {
+        assert_eq!(get_error(json!(1)), "unsupported value type");
+        assert_eq!(get_error(json!({"type": "choice"})), "the `choice` type requires a non-empty `choices` list");
+        assert_eq!(
+            get_error(json!({"type": "int", "choices": ["1"]})),
+            "the `choices` key isn't supported by the `int` type"
+        );
+        assert_eq!(
+            get_error(json!({"type": "int", "default": "8080"})),
+            "invalid default value: `\"8080\"` isn't a value of the `int` type"
+        );
+        assert_eq!(
+            get_error(json!({"choices": ["a", "b"], "default": "c"})),
+            "invalid default value: `c` isn't one of the choices: a, b"
+        );
+    }
+
+    #[test]
+    fn test_required_variables_have_no_value() {
+        assert_eq!(declare(json!({"type": "string"})).get_value(), Some(json!("")));
+        assert_eq!(declare(json!({"type": "string", "required": true})).get_value(), None);
+        assert_eq!(declare(json!({"default": "", "required": true})).get_value(), None);
+        assert_eq!(declare(json!({"default": "app", "required": true})).get_value(), Some(json!("app")));
+    }
+
+    #[test]
+    fn test_parse_input() {
+        let options = declare(json!({"type": "bool"}));
+        assert_eq!(options.parse_input("Yes"), Ok(Some(json!(true))));
+        assert_eq!(options.parse_input("off"), Ok(Some(json!(false))));
+        assert_eq!(options.parse_input(""), Ok(None));
+        assert_eq!(options.parse_input("maybe"), Err(String::from("Expected `yes` or `no`.")));
+
+        let options = declare(json!({"type": "int", "required": true}));
+        assert_eq!(options.parse_input(" 42 "), Ok(Some(json!(42))));
+        assert_eq!(options.parse_input("4.2"), Err(String::from("Expected an integer number.")));
+        assert_eq!(options.parse_input(""), Err(String::from("The value is required.")));
+
+        let options = declare(json!({"choices": ["mit", "apache"]}));
+        assert_eq!(options.parse_input("2"), Ok(Some(json!("apache"))));
+        assert_eq!(options.parse_input("mit"), Ok(Some(json!("mit"))));
+        assert_eq!(
+            options.parse_input("3"),
+            Err(String::from("Invalid value: `3` isn't one of the choices: mit, apache."))
+        );
+
+        let options = declare(json!({"type": "list", "default": []}));
+        assert_eq!(options.parse_input("a, b,,c"), Ok(Some(json!(["a", "b", "c"]))));
+        assert_eq!(options.parse_input(" , "), Err(String::from("Expected a comma separated list of values.")));
+    }
+
+    #[test]
+    fn test_text_constraints() {
+        let options = declare(json!({"pattern": "[a-z][a-z0-9-]*", "min_length": 3, "max_length": 5}));
+        assert_eq!(options.validate_value(&json!("app-1")), Ok(()));
+        assert_eq!(options.validate_value(&json!("ab")), Err(String::from("`ab` must be at least 3 character(s) long")));
+        assert_eq!(options.validate_value(&json!("abcdef")), Err(String::from("`abcdef` must be at most 5 character(s) long")));
+        // The pattern must match the whole value
+        assert_eq!(options.validate_value(&json!("1app")), Err(String::from("`1app` doesn't match the `[a-z][a-z0-9-]*` pattern")));
+        assert_eq!(options.validate_value(&json!("app!")), Err(String::from("`app!` doesn't match the `[a-z][a-z0-9-]*` pattern")));
+    }
+
+    #[test]
+    fn test_list_constraints() {
+        let options = declare(json!({"type": "list", "default": ["a"], "min_items": 1, "max_items": 2, "max_length": 3}));
+        assert_eq!(options.validate_value(&json!(["a", "b"])), Ok(()));
+        assert_eq!(options.validate_value(&json!([])), Err(String::from("the list must contain at least 1 item(s)")));
+        assert_eq!(options.validate_value(&json!(["a", "b", "c"])), Err(String::from("the list must contain at most 2 item(s)")));
+        // The text constraints are checked for every item of the list
+        assert_eq!(options.validate_value(&json!(["a", "long"])), Err(String::from("`long` must be at most 3 character(s) long")));
+    }
+
+    #[test]
+    fn test_invalid_constraints() {
+        assert_eq!(
+            get_error(json!({"type": "bool", "pattern": "y"})),
+            "the `pattern`, `min_length` and `max_length` keys aren't supported by the `bool` type"
+        );
+        assert_eq!(
+            get_error(json!({"type": "string", "min_items": 1})),
+            "the `min_items` and `max_items` keys aren't supported by the `string` type"
+        );
+        assert_eq!(
+            get_error(json!({"min_length": 5, "max_length": 2})),
+            "the `min_length` value (5) is greater than the `max_length` value (2)"
+        );
+        assert!(get_error(json!({"pattern": "("})).starts_with("the `(` pattern is invalid: "));
+        assert_eq!(
+            get_error(json!({"default": "App", "pattern": "[a-z]+"})),
+            "invalid default value: `App` doesn't match the `[a-z]+` pattern"
+        );
+    }
+}
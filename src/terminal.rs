@@ -1,8 +1,9 @@
 use read_input::InputBuild;
 use read_input::prelude::input;
-use serde_json::{json, Value as SerdeValue};
+use serde_json::Value as SerdeValue;
 
 use crate::error::Error;
+use crate::templates::variables::{VariableOptions, VariableType};
 
 static REPLACE_TEMPLATE_MESSAGE: &str =
     "The requested template already saved \
@@ -23,51 +24,64 @@ pub fn ask_for_replacing_template() -> Result<(), Error> {
     }
 }
 
+/// Asks the user for the value of the variable in according to its type.
+/// The prompt and the description of the variable are shown instead of the
+/// key when specified. Invalid values are requested again. Returns `None`
+/// when the user has left the current value.
 pub fn ask_for_input(
     template_name: &String,
     variable_name: &String,
-    default_value: &SerdeValue
+    options: &VariableOptions,
 ) -> Option<SerdeValue> {
-    println!(
-        "\nSpecify the value for the `{}:{}` key. Default is `{}`.",
-        template_name, variable_name, default_value
-    );
+    let default_message = match options.get_value() {
+        Some(value) => format!("Default is `{}`.", value),
+        None => String::from("The value is required."),
+    };
+    match (&options.prompt, &options.description) {
+        (Some(title), _) | (None, Some(title)) => println!("\n{}\n  {}", title, default_message),
+        (None, None) => println!(
+            "\nSpecify the value for the `{}:{}` key. {}",
+            template_name, variable_name, default_message
+        ),
+    }
+
+    if let (Some(_), Some(description)) = (&options.prompt, &options.description) {
+        println!("  {}", description);
+    }
 
-    let local_default = default_value.clone();
-    let input: String = input()
-        .repeat_msg("Input: ")
-        .add_test(move |raw_value: &String| {
-            match local_default {
-                SerdeValue::Array(_) => {
-                    let data: Vec<String> = raw_value
-                        .split(",")
-                        .collect::<Vec<&str>>()
-                        .iter()
-                        .filter(|raw_str| !raw_str.is_empty())
-                        .map(|raw_str| String::from(*raw_str))
-                        .collect();
-                    !data.is_empty()
-                },
-                _ => true,
-            }
-        })
-        .get();
+    if let Some(examples) = &options.examples {
+        println!("  Examples: {}", examples.join(", "));
+    }
 
-    let user_data = input.trim().to_string();
-    match user_data.is_empty() {
-        false => match default_value {
-            SerdeValue::String(_) => Some(SerdeValue::String(user_data)),
-            SerdeValue::Array(_) => {
-                let data: Vec<String> = user_data
-                    .split(",")
-                    .collect::<Vec<&str>>()
-                    .iter()
-                    .map(|raw_str| String::from(*raw_str))
-                    .collect();
-                Some(json!(data))
-            },
-            _ => None
-        },
-        true => None,
+    if options.help.is_some() {
+        println!("  Type `?` to show the help.");
+    }
+
+    let variable_type = options.get_type();
+    if let Some(choices) = &options.choices {
+        for (index, choice) in choices.iter().enumerate() {
+            println!("  {}) {}", index + 1, choice);
+        }
+    }
+
+    let input_message = match variable_type {
+        VariableType::Bool => "Input (yes/no): ",
+        VariableType::Int => "Input (integer): ",
+        VariableType::Choice => "Input (number or value): ",
+        VariableType::List => "Input (comma separated values): ",
+        VariableType::String => "Input: ",
+    };
+
+    loop {
+        let raw_value: String = input().msg(input_message).get();
+        if let (Some(help), "?") = (&options.help, raw_value.trim()) {
+            println!("{}", help);
+            continue
+        }
+
+        match options.parse_input(&raw_value) {
+            Ok(value) => return value,
+            Err(message) => println!("{}", message),
+        }
     }
 }